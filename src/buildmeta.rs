@@ -1,11 +1,11 @@
-use std::{fmt, result};
+use std::{error, fmt, result, str::FromStr};
 
 use crate::identifier;
 use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
 use git2::Repository;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct BuildMetaData {
     number: usize,
@@ -19,16 +19,20 @@ pub type Result<T> = result::Result<T, BuildMetaError>;
 #[derive(Debug)]
 pub enum BuildMetaError {
     Undefined,
-    Format(String),
+    Format(identifier::FormatError),
+    UnknownPlaceholder(String),
+    DateFormat(chrono::ParseError),
     Overflow(usize),
-    Git(String),
+    Git(git2::Error),
     Date,
 }
 
 impl fmt::Display for BuildMetaError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            BuildMetaError::Format(s) => write!(f, "フォーマットが不正です: {}", s),
+            BuildMetaError::Format(e) => write!(f, "フォーマットが不正です: {}", e),
+            BuildMetaError::UnknownPlaceholder(s) => write!(f, "不明な書式です: {{{}}}", s),
+            BuildMetaError::DateFormat(e) => write!(f, "日付の書式が不正です: {}", e),
             BuildMetaError::Git(s) => write!(f, "Git情報の取得に失敗しました: {}", s),
             BuildMetaError::Date => write!(f, "日時の取得に失敗しました"),
             BuildMetaError::Overflow(n) => write!(f, "数値が指定できる範囲を超えています: {}+1", n),
@@ -37,6 +41,29 @@ impl fmt::Display for BuildMetaError {
     }
 }
 
+impl error::Error for BuildMetaError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BuildMetaError::Format(e) => Some(e),
+            BuildMetaError::DateFormat(e) => Some(e),
+            BuildMetaError::Git(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<identifier::FormatError> for BuildMetaError {
+    fn from(e: identifier::FormatError) -> Self {
+        BuildMetaError::Format(e)
+    }
+}
+
+impl From<git2::Error> for BuildMetaError {
+    fn from(e: git2::Error) -> Self {
+        BuildMetaError::Git(e)
+    }
+}
+
 impl BuildMetaData {
     pub fn new(number: usize, date: String, hash: String, format: String) -> BuildMetaData {
         BuildMetaData {
@@ -47,47 +74,19 @@ impl BuildMetaData {
         }
     }
 
-    /// Print Full BuildMetaData
-    /// example: `+build.123.20250220.fef16c61`
-    pub fn show_all(&self) -> Result<()> {
-        println!("{}", &self);
-        Ok(())
+    /// BuildMetaData with the config format
+    pub fn format_string(&self) -> Result<String> {
+        self.create_string().map_err(BuildMetaError::Format)
     }
 
-    /// Print from Config Format
-    pub fn show(&self) -> Result<()> {
-        match self.create_string() {
-            Ok(s) => {
-                println!("{}", s);
-                Ok(())
-            }
-            Err(e) => Err(BuildMetaError::Format(e.to_string())),
-        }
+    /// Build date as `%Y%m%d`
+    pub fn date_string(&self) -> Result<String> {
+        self.fmt_string("{date:%Y%m%d}")
     }
 
-    pub fn show_number(&self) -> Result<()> {
-        println!("{}", self.number);
-        Ok(())
-    }
-
-    pub fn show_date(&self) -> Result<()> {
-        match self.create_fmt_string("{date:%Y%m%d}") {
-            Ok(s) => {
-                println!("{}", s);
-                Ok(())
-            }
-            Err(e) => Err(BuildMetaError::Format(e.to_string())),
-        }
-    }
-
-    pub fn show_hash(&self) -> Result<()> {
-        match self.create_fmt_string("{hash:8}") {
-            Ok(s) => {
-                println!("{}", s);
-                Ok(())
-            }
-            Err(e) => Err(BuildMetaError::Format(e.to_string())),
-        }
+    /// First 8 digits of the build hash
+    pub fn hash_string(&self) -> Result<String> {
+        self.fmt_string("{hash:8}")
     }
 
     fn create_string(&self) -> identifier::Result<String> {
@@ -139,13 +138,13 @@ impl BuildMetaData {
                 self.hash_haed(n)
             }
             _ => {
-                return Err(BuildMetaError::Format(opt.to_string()));
+                return Err(BuildMetaError::UnknownPlaceholder(opt.to_string()));
             }
         };
         Ok(ret)
     }
 
-    /// Print BuildMetaData with format-string
+    /// BuildMetaData with format-string
     ///
     /// ## formats
     /// | format               | input                            | export                         | note                                                  |
@@ -153,12 +152,8 @@ impl BuildMetaData {
     /// | number,<br>num,<br>n | `build.{number}`<br>`build{num}` | `build.123`<br>`build123`      |                                                       |
     /// | date,<br>d           | `date.{date}`<br>`{d:%Y/%m/%d %H:%M}`           | `date.20250220`<br>`20250220`  | future: strftime support                              |
     /// | hash,<br>h           | `hash.{hash}`<br>`{hash:4}`      | `hash.fef16c61`<br>`hash.fef1` | After `:`, specify display digits (default: 8 digits) |
-    pub fn show_fmt(&self, fmt: &str) -> Result<()> {
-        let t = self
-            .create_fmt_string(fmt)
-            .map_err(|e| BuildMetaError::Format(e.to_string()))?;
-        println!("{}", t);
-        Ok(())
+    pub fn fmt_string(&self, fmt: &str) -> Result<String> {
+        self.create_fmt_string(fmt).map_err(BuildMetaError::Format)
     }
 
     fn create_fmt_string(&self, fmt: &str) -> identifier::Result<String> {
@@ -216,7 +211,7 @@ impl BuildMetaData {
     /// # Errors
     /// git2::Error
     pub fn update_hash(&mut self) -> Result<()> {
-        let hash = get_commit_hash().map_err(|e| BuildMetaError::Git(e.to_string()))?;
+        let hash = get_commit_hash()?;
         self.hash = hash;
         Ok(())
     }
@@ -247,8 +242,8 @@ impl BuildMetaData {
 
     /// Need format "%Y/%m/%d"
     pub fn set_date(&mut self, s: &str) -> Result<()> {
-        let naive_date = NaiveDate::parse_from_str(s, "%Y/%m/%d")
-            .map_err(|e| BuildMetaError::Format(e.to_string()))?;
+        let naive_date =
+            NaiveDate::parse_from_str(s, "%Y/%m/%d").map_err(BuildMetaError::DateFormat)?;
         let naive_datetime = naive_date
             .and_hms_opt(0, 0, 0)
             .ok_or(BuildMetaError::Date)?;
@@ -269,7 +264,7 @@ impl BuildMetaData {
 
     pub fn set_format(&mut self, fmt: &str) -> Result<()> {
         self.create_fmt_string(fmt)
-            .map_err(|e| BuildMetaError::Format(e.to_string()))?;
+            .map_err(BuildMetaError::Format)?;
         self.format = fmt.to_string();
         Ok(())
     }
@@ -294,14 +289,49 @@ impl Default for BuildMetaData {
 }
 
 impl fmt::Display for BuildMetaData {
+    /// BuildMetaData with the config format, same as [`BuildMetaData::format_string`].
+    ///
+    /// When the format cannot be rendered (e.g. `{hash}` is empty), only the
+    /// build number is written so the output stays a valid identifier.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.create_fmt_string("build.{num}.date.{date:%Y%m%d}.hash.{hash:8}") {
+        match self.format_string() {
             Ok(s) => write!(f, "{}", s),
-            Err(e) => write!(f, "{}", e),
+            Err(_) => write!(f, "{}", self.number),
         }
     }
 }
 
+impl FromStr for BuildMetaData {
+    type Err = BuildMetaError;
+
+    /// Parse build metadata of a SemVer string (e.g. `build.5`).
+    ///
+    /// The first numeric identifier becomes the build number and is kept in the
+    /// format as `{number}`. date and hash are left empty.
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        identifier::check_dot_separated_identifiers(s).map_err(BuildMetaError::Format)?;
+
+        let mut number = None;
+        let parts: Vec<String> = s
+            .split(".")
+            .map(|part| match part.parse::<usize>() {
+                Ok(n) if number.is_none() && part == n.to_string() => {
+                    number = Some(n);
+                    "{number}".to_string()
+                }
+                _ => part.to_string(),
+            })
+            .collect();
+
+        Ok(BuildMetaData {
+            number: number.unwrap_or_default(),
+            date: String::new(),
+            hash: String::new(),
+            format: parts.join("."),
+        })
+    }
+}
+
 fn get_commit_hash() -> result::Result<String, git2::Error> {
     let repo = Repository::open(".")?;
     let head = repo.head()?;
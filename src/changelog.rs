@@ -0,0 +1,350 @@
+use std::{
+    error, fmt, fs, io,
+    path::{Path, PathBuf},
+    result,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::conventional::ConventionalCommit;
+use crate::git::CommitInfo;
+
+pub type Result<T> = result::Result<T, ChangelogError>;
+
+#[derive(Debug)]
+pub enum ChangelogError {
+    IO(PathBuf, io::Error),
+    AlreadyExists(PathBuf, String),
+}
+
+impl fmt::Display for ChangelogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangelogError::IO(p, e) => write!(f, "{}: {}", p.display(), e),
+            ChangelogError::AlreadyExists(p, h) => {
+                write!(f, "{}: {} はすでに書かれています", p.display(), h)
+            }
+        }
+    }
+}
+
+impl error::Error for ChangelogError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ChangelogError::IO(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Type of [`Section`] collecting breaking changes of any type
+pub const BREAKING: &str = "breaking";
+
+/// `[changelog]` section
+///
+/// ```toml
+/// [changelog]
+/// file = "CHANGELOG.md"
+/// header = "## {version} - {date}"
+/// date = "%Y-%m-%d"
+/// section = "### {title}"
+/// item = "- {scope}{description} ({hash})"
+/// sections = [
+///     { type = "breaking", title = "Breaking Changes" },
+///     { type = "feat", title = "Features" },
+///     { type = "fix", title = "Bug Fixes" },
+///     { type = "perf", title = "Performance" },
+/// ]
+/// ```
+///
+/// `file` is relative to the directory of the config file.
+/// Commits whose type is not listed in `sections` are left out.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChangelogConfig {
+    #[serde(default = "default_file")]
+    pub file: PathBuf,
+    /// `{version}` and `{date}` are replaced
+    #[serde(default = "default_header")]
+    pub header: String,
+    /// strftime format of `{date}`
+    #[serde(default = "default_date")]
+    pub date: String,
+    /// `{title}` is replaced
+    #[serde(default = "default_section")]
+    pub section: String,
+    /// `{scope}` (`**scope:** ` or empty), `{description}`, `{summary}` and `{hash}` are replaced
+    #[serde(default = "default_item")]
+    pub item: String,
+    #[serde(default = "default_sections")]
+    pub sections: Vec<Section>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// commit type, or [`BREAKING`]
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+}
+
+fn default_file() -> PathBuf {
+    PathBuf::from("CHANGELOG.md")
+}
+
+fn default_header() -> String {
+    "## {version} - {date}".to_string()
+}
+
+fn default_date() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+fn default_section() -> String {
+    "### {title}".to_string()
+}
+
+fn default_item() -> String {
+    "- {scope}{description} ({hash})".to_string()
+}
+
+fn default_sections() -> Vec<Section> {
+    [
+        (BREAKING, "Breaking Changes"),
+        ("feat", "Features"),
+        ("fix", "Bug Fixes"),
+        ("perf", "Performance"),
+    ]
+    .into_iter()
+    .map(|(kind, title)| Section {
+        kind: kind.to_string(),
+        title: title.to_string(),
+    })
+    .collect()
+}
+
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        Self {
+            file: default_file(),
+            header: default_header(),
+            date: default_date(),
+            section: default_section(),
+            item: default_item(),
+            sections: default_sections(),
+        }
+    }
+}
+
+/// Planned edit of the changelog
+#[derive(Debug, Clone)]
+pub struct ChangelogEdit {
+    /// `file` joined to the base directory
+    pub path: PathBuf,
+    /// first line of `section`
+    pub heading: String,
+    pub section: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl ChangelogConfig {
+    /// Markdown section of `version` listing `commits` by type.
+    pub fn render(&self, version: &str, date: &str, commits: &[CommitInfo]) -> String {
+        let parsed: Vec<(&CommitInfo, ConventionalCommit)> = commits
+            .iter()
+            .filter_map(|c| Some((c, ConventionalCommit::parse(&c.message)?)))
+            .collect();
+        let has_breaking = self.sections.iter().any(|s| s.kind == BREAKING);
+
+        let mut out = self
+            .header
+            .replace("{version}", version)
+            .replace("{date}", date);
+        out.push('\n');
+        for section in &self.sections {
+            let items: Vec<String> = parsed
+                .iter()
+                .filter(|(_, cc)| match section.kind.as_str() {
+                    BREAKING => cc.breaking,
+                    kind => cc.kind == kind && !(has_breaking && cc.breaking),
+                })
+                .map(|(c, cc)| self.render_item(c, cc))
+                .collect();
+            if items.is_empty() {
+                continue;
+            }
+            out.push('\n');
+            out.push_str(&self.section.replace("{title}", &section.title));
+            out.push_str("\n\n");
+            for item in items {
+                out.push_str(&item);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    fn render_item(&self, commit: &CommitInfo, cc: &ConventionalCommit) -> String {
+        let scope = match &cc.scope {
+            Some(s) => format!("**{}:** ", s),
+            None => String::new(),
+        };
+        self.item
+            .replace("{scope}", &scope)
+            .replace("{description}", &cc.description)
+            .replace("{summary}", &commit.summary)
+            .replace("{hash}", commit.id.get(..8).unwrap_or(&commit.id))
+    }
+
+    /// Read the changelog and compute the new contents, without writing.
+    ///
+    /// # Errors
+    /// * the changelog cannot be read
+    /// * the changelog already has the heading of `version`
+    pub fn plan(
+        &self,
+        base: &Path,
+        version: &str,
+        date: &str,
+        commits: &[CommitInfo],
+    ) -> Result<ChangelogEdit> {
+        let path = base.join(&self.file);
+        let before = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(ChangelogError::IO(path, e)),
+        };
+        let section = self.render(version, date, commits);
+        let heading = section.lines().next().unwrap_or_default().to_string();
+        if before.lines().any(|l| l == heading) {
+            return Err(ChangelogError::AlreadyExists(path, heading));
+        }
+        let after = self.prepend(&before, &section);
+        Ok(ChangelogEdit {
+            path,
+            heading,
+            section,
+            before,
+            after,
+        })
+    }
+
+    /// Insert `section` before the first entry of `changelog`.
+    ///
+    /// Entries are the headings of the same level as `header`. Text before
+    /// them (the title) and the older entries are kept as they are.
+    fn prepend(&self, changelog: &str, section: &str) -> String {
+        if changelog.trim().is_empty() {
+            return format!("# Changelog\n\n{}", section);
+        }
+        let level = self.header.chars().take_while(|c| *c == '#').count();
+        let marker = format!("{} ", "#".repeat(level));
+        let mut offset = 0;
+        for line in changelog.split_inclusive('\n') {
+            if level > 0 && line.starts_with(&marker) {
+                return format!(
+                    "{}{}\n{}",
+                    &changelog[..offset],
+                    section,
+                    &changelog[offset..]
+                );
+            }
+            offset += line.len();
+        }
+        // エントリがまだない
+        let sep = if changelog.ends_with("\n\n") {
+            ""
+        } else if changelog.ends_with('\n') {
+            "\n"
+        } else {
+            "\n\n"
+        };
+        format!("{}{}{}", changelog, sep, section)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn commit(id: &str, message: &str) -> CommitInfo {
+        CommitInfo {
+            id: id.to_string(),
+            summary: message.lines().next().unwrap().to_string(),
+            message: message.to_string(),
+            time: 0,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let commits = [
+            commit("aaaaaaaaaa", "feat(cli): add changelog"),
+            commit("bbbbbbbbbb", "chore: deps"),
+            commit("cccccccccc", "fix: crash"),
+            commit("dddddddddd", "feat!: drop v1 config"),
+        ];
+        let config = ChangelogConfig::default();
+        assert_eq!(
+            config.render("1.3.0+build.5", "2025-03-04", &commits),
+            "## 1.3.0+build.5 - 2025-03-04\n\
+             \n### Breaking Changes\n\n- drop v1 config (dddddddd)\n\
+             \n### Features\n\n- **cli:** add changelog (aaaaaaaa)\n\
+             \n### Bug Fixes\n\n- crash (cccccccc)\n"
+        );
+
+        let config = ChangelogConfig {
+            header: "# v{version}".to_string(),
+            item: "* {summary}".to_string(),
+            sections: vec![Section {
+                kind: "feat".to_string(),
+                title: "New".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.render("2.0.0", "", &commits),
+            "# v2.0.0\n\n### New\n\n* feat(cli): add changelog\n* feat!: drop v1 config\n"
+        );
+    }
+
+    #[test]
+    fn test_prepend() {
+        let config = ChangelogConfig::default();
+        let section = "## 1.1.0 - 2025-03-04\n\n- new\n";
+        assert_eq!(
+            config.prepend("", section),
+            "# Changelog\n\n## 1.1.0 - 2025-03-04\n\n- new\n"
+        );
+
+        let old = "# Changelog\n\nAll notable changes.\n\n## 1.0.0 - 2025-01-01\n\n- first\n";
+        assert_eq!(
+            config.prepend(old, section),
+            "# Changelog\n\nAll notable changes.\n\n## 1.1.0 - 2025-03-04\n\n- new\n\n## 1.0.0 - 2025-01-01\n\n- first\n"
+        );
+        assert_eq!(
+            config.prepend("# Changelog", section),
+            "# Changelog\n\n## 1.1.0 - 2025-03-04\n\n- new\n"
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let dir = env::temp_dir().join(format!("fuver-changelog-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = ChangelogConfig::default();
+        let commits = [commit("aaaaaaaaaa", "fix: crash")];
+
+        let edit = config.plan(&dir, "1.0.1", "today", &commits).unwrap();
+        assert_eq!(edit.heading, "## 1.0.1 - today");
+        assert_eq!(edit.before, "");
+        fs::write(&edit.path, &edit.after).unwrap();
+
+        assert!(matches!(
+            config.plan(&dir, "1.0.1", "today", &commits),
+            Err(ChangelogError::AlreadyExists(..))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,12 +1,23 @@
 use std::{
-    env,
-    fs::{self, File},
+    env, fs, io,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use crate::fuver::{self, FuVer, FuVerError};
+use crate::changelog::ChangelogEdit;
+use crate::config;
+use crate::conventional::{self, BumpReport};
+use crate::diff;
+use crate::fuver::{self, Change, FuVer, FuVerError};
+use crate::git;
+use crate::lock::ConfigLock;
+use crate::output::{OutputFormat, VersionInfo};
+use crate::pre::PreReleaseError;
+use crate::storage;
+use crate::verify;
+use crate::version::VersionPart;
+use chrono::{DateTime, Local};
 use clap::Parser;
-use std::str::FromStr;
 
 const DEFAULT_FILE: &str = concat!(env!("CARGO_PKG_NAME"), ".toml");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -26,6 +37,23 @@ enum ShowVersionTarget {
     Patch,
 }
 
+#[derive(clap::ValueEnum, Debug, Clone)]
+enum NextVersionTarget {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl From<NextVersionTarget> for VersionPart {
+    fn from(target: NextVersionTarget) -> Self {
+        match target {
+            NextVersionTarget::Major => VersionPart::Major,
+            NextVersionTarget::Minor => VersionPart::Minor,
+            NextVersionTarget::Patch => VersionPart::Patch,
+        }
+    }
+}
+
 #[derive(clap::ValueEnum, Debug, Clone)]
 enum PreReleaseTarget {
     Tag,
@@ -58,8 +86,31 @@ enum IncrementCommands {
     Major,
     Minor,
     Patch,
+    /// Increment pre-release, or start one on the next patch version
     #[command(visible_alias = "pre")]
-    PreRelease,
+    PreRelease {
+        /// Restart the pre-release with this tag when it differs from the current one
+        #[arg(conflicts_with = "stage")]
+        tag: Option<String>,
+        /// Move to the next stage (e.g. alpha -> beta) and reset the number
+        #[arg(long)]
+        stage: bool,
+        /// Release when the current stage is the last one
+        #[arg(long, requires = "stage")]
+        release: bool,
+    },
+    /// Increment major version and start a new pre-release
+    Premajor {
+        tag: Option<String>,
+    },
+    /// Increment minor version and start a new pre-release
+    Preminor {
+        tag: Option<String>,
+    },
+    /// Increment patch version and start a new pre-release
+    Prepatch {
+        tag: Option<String>,
+    },
     #[command(visible_alias = "build")]
     BuildMetaData {
         target: Option<BuildMetaDataTarget>,
@@ -99,6 +150,14 @@ enum SetCommands {
     },
 }
 
+#[derive(clap::Subcommand, Debug)]
+enum UnsetCommands {
+    #[command(visible_alias = "pre")]
+    PreRelease,
+    #[command(visible_alias = "build")]
+    BuildMetaData,
+}
+
 #[derive(clap::Subcommand, Debug)]
 enum ShowCommands {
     #[command(visible_alias = "ver")]
@@ -129,6 +188,12 @@ enum Commands {
     Init {
         #[arg(default_value = DEFAULT_FILE)]
         file: String,
+        /// Start from the highest version tag reachable from HEAD
+        #[arg(long)]
+        from_git: bool,
+        /// Tag prefix for --from-git
+        #[arg(long, default_value = "v", requires = "from_git")]
+        prefix: String,
     },
     #[command(visible_alias = "incr")]
     Increment {
@@ -143,156 +208,608 @@ enum Commands {
         #[command(subcommand)]
         target: SetCommands,
     },
+    #[command(visible_alias = "clear")]
+    Unset {
+        #[arg(short, long)]
+        silent: bool,
+        #[command(subcommand)]
+        target: UnsetCommands,
+    },
+    /// Drop the pre-release and finalize the version
+    Release {
+        #[arg(short, long)]
+        silent: bool,
+        /// Refresh build metadata (number, date and hash)
+        #[arg(short, long)]
+        build: bool,
+        /// Open the next development cycle
+        #[arg(short, long, value_enum)]
+        next: Option<NextVersionTarget>,
+        /// Pre-release tag of the next development cycle
+        #[arg(short, long, requires = "next")]
+        pre: Option<String>,
+    },
     Show {
         #[command(subcommand)]
         target: Option<ShowCommands>,
     },
+    /// Increment the version from Conventional Commits since the last release tag
+    ///
+    /// feat: minor, fix/perf: patch, `!` or BREAKING CHANGE: major
+    Bump {
+        #[arg(short, long)]
+        silent: bool,
+        /// Accepted for compatibility. The part is always decided from the commits
+        #[arg(long)]
+        auto: bool,
+    },
+    /// Prepend the commits since the previous release tag to the changelog
+    Changelog {
+        #[arg(short, long)]
+        silent: bool,
+    },
+    /// Create an annotated release tag of the version at HEAD
+    Tag {
+        #[arg(short, long)]
+        silent: bool,
+        /// Tag a dirty worktree and replace an existing tag
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Check the release tag, the config commit and the version order for CI
+    ///
+    /// Exit codes: 3 tag mismatch, 4 config not committed, 5 version not increased
+    Verify {
+        #[arg(short, long)]
+        silent: bool,
+        /// Tag being built (e.g. $GITHUB_REF_NAME). Default: the tags at HEAD
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// Print the version `increment` would produce, without writing
+    Next {
+        #[command(subcommand)]
+        target: IncrementCommands,
+    },
+    /// Copy the version to the files listed in `[sync]`
+    Sync {
+        #[arg(short, long)]
+        silent: bool,
+        /// Fail when a file is out of date, without writing
+        #[arg(long)]
+        check: bool,
+    },
     Version,
+    /// Print the path of the config file in use
+    Which,
 }
 
 #[derive(clap::Parser, Debug)]
 pub struct Args {
-    #[arg(short, long, default_value = DEFAULT_FILE)]
+    /// Config file (default: FUVER_CONFIG, or fuver.toml in the current or a parent directory)
+    #[arg(short, long)]
     config: Option<String>,
 
+    /// Output format. json prints `show` as an object and changes as `[{field, action, old, new}]`
+    #[arg(short, long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Print the result and the diff without writing any file
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Seconds to wait for another fuver process updating the config
+    #[arg(long, default_value = "10", value_parser = parse_timeout)]
+    lock_timeout: Duration,
+
     #[command(subcommand)]
     cmd: Commands,
 }
 
-fn run_increment(fv: &mut FuVer, cmd: IncrementCommands, silent: bool) -> fuver::Result<()> {
-    match cmd {
+/// Seconds as a `Duration`. Negative, NaN and too large values are rejected.
+fn parse_timeout(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    Duration::try_from_secs_f64(secs).map_err(|e| format!("{}: {}", s, e))
+}
+
+fn run_increment(fv: &mut FuVer, cmd: IncrementCommands) -> fuver::Result<Vec<Change>> {
+    let change = match cmd {
         IncrementCommands::Version { target } => match target {
-            IncrVersionTarget::Major => fv.incr_ver_major(silent),
-            IncrVersionTarget::Minor => fv.incr_ver_minor(silent),
-            IncrVersionTarget::Patch => fv.incr_ver_patch(silent),
-            IncrVersionTarget::Mask { pattern } => fv.incr_ver_mask(&pattern, silent),
+            IncrVersionTarget::Major => fv.incr_ver_major(),
+            IncrVersionTarget::Minor => fv.incr_ver_minor(),
+            IncrVersionTarget::Patch => fv.incr_ver_patch(),
+            IncrVersionTarget::Mask { pattern } => fv.incr_ver_mask(&pattern),
         },
-        IncrementCommands::Major => fv.incr_ver_major(silent),
-        IncrementCommands::Minor => fv.incr_ver_minor(silent),
-        IncrementCommands::Patch => fv.incr_ver_patch(silent),
+        IncrementCommands::Major => fv.incr_ver_major(),
+        IncrementCommands::Minor => fv.incr_ver_minor(),
+        IncrementCommands::Patch => fv.incr_ver_patch(),
 
-        IncrementCommands::PreRelease => fv.incr_pre(silent),
+        IncrementCommands::PreRelease {
+            tag,
+            stage,
+            release,
+        } => {
+            if stage {
+                return fv.incr_pre_stage(release);
+            }
+            fv.incr_prerelease(tag.as_deref())
+        }
+        IncrementCommands::Premajor { tag } => fv.incr_premajor(tag.as_deref()),
+        IncrementCommands::Preminor { tag } => fv.incr_preminor(tag.as_deref()),
+        IncrementCommands::Prepatch { tag } => fv.incr_prepatch(tag.as_deref()),
 
         IncrementCommands::BuildMetaData { target } => match target {
             Some(t) => match t {
-                BuildMetaDataTarget::Number => fv.incr_build_num(silent),
-                BuildMetaDataTarget::Date => fv.incr_build_date(silent),
-                BuildMetaDataTarget::Hash => fv.incr_build_hash(silent),
-                BuildMetaDataTarget::All => fv.incr_build_all(silent),
+                BuildMetaDataTarget::Number => fv.incr_build_num(),
+                BuildMetaDataTarget::Date => fv.incr_build_date(),
+                BuildMetaDataTarget::Hash => fv.incr_build_hash(),
+                BuildMetaDataTarget::All => fv.incr_build_all(),
             },
-            None => fv.incr_build_num(silent),
+            None => fv.incr_build_num(),
         },
     }?;
-    Ok(())
+    Ok(vec![change])
+}
+
+/// Initial `FuVer` from the highest tag `{prefix}<version>` and the commits since it.
+fn initial_from_git(dir: &Path, prefix: &str) -> fuver::Result<FuVer> {
+    let repo = git::open(dir)?;
+    let config = git::GitConfig::with_prefix(prefix);
+    let tag = git::last_release(&repo, &config)?.ok_or_else(|| {
+        FuVerError::InitError(format!(
+            "No tag {} reachable from HEAD.",
+            config.tag_name("<version>")
+        ))
+    })?;
+    let commits = git::commits_since(&repo, Some(tag.commit))?.len();
+    eprintln!("tag {} (+{} commits)", tag.name, commits);
+    let head = repo.head()?.peel_to_commit()?.id().to_string();
+    let mut fv = FuVer::from_release(&tag.version, commits, &head)?;
+    fv.set_git(config);
+    Ok(fv)
 }
 
-fn run_init(file: &str) -> fuver::Result<()> {
+fn run_init(file: &str, from_git: Option<&str>, dry_run: bool) -> fuver::Result<()> {
     let p = if Path::new(file).is_absolute() {
         PathBuf::from(file)
     } else {
-        std::env::current_dir().map_err(FuVerError::IO)?.join(file)
+        std::env::current_dir()?.join(file)
     };
-    if p.exists() {
-        return Err(FuVerError::InitError("Already initialized.".to_string()));
+    let storage = storage::for_path(&p);
+    let mut fv = storage.initial(&p)?;
+    if let Some(prefix) = from_git {
+        fv = initial_from_git(p.parent().unwrap_or(Path::new(".")), prefix)?;
     }
-
-    if let Some(parent) = p.parent() {
-        std::fs::create_dir_all(parent).map_err(FuVerError::IO)?;
+    if dry_run {
+        println!("version {}", &fv);
+        print_diff(&p, &read_or_empty(&p)?, &storage.render(&fv, &p)?);
+        return Ok(());
     }
-    File::create(&p).map_err(FuVerError::IO)?;
-    let default = FuVer::default();
-    let toml_str = toml::to_string(&default).map_err(|e| FuVerError::InitError(e.to_string()))?;
-    fs::write(&p, toml_str).map_err(FuVerError::IO)?;
+    storage.save(&fv, &p)?;
     println!("Initialize Success!");
     println!("file {}", p.to_string_lossy());
-    println!("version {}", &default);
+    println!("version {}", &fv);
     Ok(())
 }
 
-fn run_show(fv: &FuVer, cmd: ShowCommands) -> fuver::Result<()> {
-    match cmd {
+fn run_show(fv: &FuVer, cmd: ShowCommands) -> fuver::Result<String> {
+    let version = fv.get_version();
+    let s = match cmd {
         ShowCommands::Version { target } => match target {
-            Some(ShowVersionTarget::Major) => fv.show_major(),
-            Some(ShowVersionTarget::Minor) => fv.show_minor(),
-            Some(ShowVersionTarget::Patch) => fv.show_patch(),
-            None => fv.show_version(),
-        },
-        ShowCommands::Major => fv.show_major(),
-        ShowCommands::Minor => fv.show_minor(),
-        ShowCommands::Patch => fv.show_patch(),
-        ShowCommands::PreRelease { target } => match target {
-            Some(t) => match t {
-                PreReleaseTarget::Tag => fv.show_prerelease_tag(),
-                PreReleaseTarget::Number => fv.show_prerelease_number(),
-            },
-            None => fv.show_prerelease(),
+            Some(ShowVersionTarget::Major) => version.get_major().to_string(),
+            Some(ShowVersionTarget::Minor) => version.get_minor().to_string(),
+            Some(ShowVersionTarget::Patch) => version.get_patch().to_string(),
+            None => version.to_string(),
         },
+        ShowCommands::Major => version.get_major().to_string(),
+        ShowCommands::Minor => version.get_minor().to_string(),
+        ShowCommands::Patch => version.get_patch().to_string(),
+        ShowCommands::PreRelease { target } => {
+            let pre = fv.get_pre()?;
+            match target {
+                Some(t) => match t {
+                    PreReleaseTarget::Tag => pre.get_tag(),
+                    PreReleaseTarget::Number => match pre.get_number() {
+                        Some(n) => n.to_string(),
+                        None => {
+                            return Err(PreReleaseError::NotDefined("number".to_string()).into())
+                        }
+                    },
+                },
+                None => pre.to_string(),
+            }
+        }
         ShowCommands::BuildMetaData { target, format } => {
             if let Some(fmt) = format {
-                return fv.show_build_fmt(&fmt);
+                return fv.get_build_fmt(&fmt);
             }
+            let build = fv.get_build()?;
             match target {
                 Some(t) => match t {
-                    BuildMetaDataTarget::Number => fv.show_build_number(),
-                    BuildMetaDataTarget::Date => fv.show_build_date(),
-                    BuildMetaDataTarget::Hash => fv.show_build_hash(),
-                    BuildMetaDataTarget::All => fv.show_build_all(),
+                    BuildMetaDataTarget::Number => build.get_number().to_string(),
+                    BuildMetaDataTarget::Date => build.date_string()?,
+                    BuildMetaDataTarget::Hash => build.hash_string()?,
+                    BuildMetaDataTarget::All => build.to_string(),
                 },
-                None => fv.show_build(),
+                None => build.format_string()?,
             }
         }
-        ShowCommands::Date => fv.show_build_date(),
-        ShowCommands::Hash => fv.show_build_hash(),
-        ShowCommands::Full => fv.show_full(),
-    }?;
-    Ok(())
+        ShowCommands::Date => fv.get_build()?.date_string()?,
+        ShowCommands::Hash => fv.get_build()?.hash_string()?,
+        ShowCommands::Full => fv.full_string()?,
+    };
+    Ok(s)
 }
 
-fn run_set(fv: &mut FuVer, cmd: SetCommands, silent: bool) -> fuver::Result<()> {
-    match cmd {
-        SetCommands::Version { version } => fv.set_version(&version, silent),
-        SetCommands::Major { version } => fv.set_major(version, silent),
-        SetCommands::Minor { version } => fv.set_minor(version, silent),
-        SetCommands::Patch { version } => fv.set_patch(version, silent),
-        SetCommands::PreRelease { tag, number } => fv.set_pre(&tag, number, silent),
+/// Key of [`VersionInfo`] printed by `cmd`.
+///
+/// `None` when the output is not one of the fields (the whole build metadata
+/// or a custom format).
+fn info_key(cmd: &ShowCommands) -> Option<&'static str> {
+    let key = match cmd {
+        ShowCommands::Version { target } => match target {
+            Some(ShowVersionTarget::Major) => "major",
+            Some(ShowVersionTarget::Minor) => "minor",
+            Some(ShowVersionTarget::Patch) => "patch",
+            None => "version",
+        },
+        ShowCommands::Major => "major",
+        ShowCommands::Minor => "minor",
+        ShowCommands::Patch => "patch",
+        ShowCommands::PreRelease { target } => match target {
+            Some(PreReleaseTarget::Tag) => "pre_tag",
+            Some(PreReleaseTarget::Number) => "pre_number",
+            None => "pre",
+        },
+        ShowCommands::BuildMetaData {
+            format: Some(_), ..
+        } => return None,
+        ShowCommands::BuildMetaData { target, .. } => match target {
+            Some(BuildMetaDataTarget::Number) => "build_number",
+            Some(BuildMetaDataTarget::Date) => "build_date",
+            Some(BuildMetaDataTarget::Hash) => "build_hash",
+            Some(BuildMetaDataTarget::All) => return None,
+            None => "build",
+        },
+        ShowCommands::Date => "build_date",
+        ShowCommands::Hash => "build_hash",
+        ShowCommands::Full => "full",
+    };
+    Some(key)
+}
+
+fn run_set(fv: &mut FuVer, cmd: SetCommands) -> fuver::Result<Vec<Change>> {
+    let change = match cmd {
+        SetCommands::Version { version } => fv.set_version(&version),
+        SetCommands::Major { version } => fv.set_major(version),
+        SetCommands::Minor { version } => fv.set_minor(version),
+        SetCommands::Patch { version } => fv.set_patch(version),
+        SetCommands::PreRelease { tag, number } => fv.set_pre(&tag, number),
         SetCommands::BuildMetaData { target } => match target {
-            SetBuildMetaDataTarget::Number { value } => fv.set_build_number(value, silent),
-            SetBuildMetaDataTarget::Date { value } => fv.set_build_date(&value, silent),
-            SetBuildMetaDataTarget::Hash { value } => fv.set_build_hash(&value, silent),
-            SetBuildMetaDataTarget::Format { value } => fv.set_build_fmt(&value, silent),
+            SetBuildMetaDataTarget::Number { value } => fv.set_build_number(value),
+            SetBuildMetaDataTarget::Date { value } => fv.set_build_date(&value),
+            SetBuildMetaDataTarget::Hash { value } => fv.set_build_hash(&value),
+            SetBuildMetaDataTarget::Format { value } => fv.set_build_fmt(&value),
         },
-        SetCommands::Date { value } => fv.set_build_date(&value, silent),
-        SetCommands::Hash { value } => fv.set_build_hash(&value, silent),
+        SetCommands::Date { value } => fv.set_build_date(&value),
+        SetCommands::Hash { value } => fv.set_build_hash(&value),
+    }?;
+    Ok(vec![change])
+}
+
+fn run_unset(fv: &mut FuVer, cmd: UnsetCommands) -> fuver::Result<Vec<Change>> {
+    let change = match cmd {
+        UnsetCommands::PreRelease => fv.unset_pre(),
+        UnsetCommands::BuildMetaData => fv.unset_build(),
+    }?;
+    Ok(vec![change])
+}
+
+fn run_bump(fv: &mut FuVer, conf_path: &Path) -> fuver::Result<(Vec<Change>, BumpReport)> {
+    let repo = git::open(conf_path.parent().unwrap_or(Path::new(".")))?;
+    let last = git::last_release(&repo, &fv.get_git())?;
+    let commits = git::commits_since(&repo, last.as_ref().map(|t| t.commit))?;
+    let report = conventional::analyze(last.as_ref().map(|t| t.name.clone()), &commits);
+    let Some(part) = report.bump else {
+        return Ok((vec![], report));
+    };
+    // タグの版から上げるので、次のタグまでに何度実行しても一度だけ上がる
+    let mut target = last.map(|t| t.version).unwrap_or_else(|| fv.clone());
+    target.incr_ver(part)?;
+    if *fv >= target {
+        return Ok((vec![], report));
+    }
+    Ok((vec![fv.bump_to(&target)?], report))
+}
+
+fn run_changelog(fv: &FuVer, conf_path: &Path) -> fuver::Result<ChangelogEdit> {
+    let base = conf_path.parent().unwrap_or(Path::new("."));
+    let repo = git::open(base)?;
+    let previous = git::previous_release(&repo, &fv.get_git(), fv)?;
+    let commits = git::commits_since(&repo, previous.map(|t| t.commit))?;
+
+    let config = fv.get_changelog();
+    // ビルド日時がなければ今日の日付
+    let date = fv
+        .get_build()
+        .ok()
+        .and_then(|b| DateTime::parse_from_rfc3339(&b.get_date()).ok())
+        .map(|d| d.format(&config.date).to_string())
+        .unwrap_or_else(|| Local::now().format(&config.date).to_string());
+    Ok(config.plan(base, &fv.full_string()?, &date, &commits)?)
+}
+
+fn print_report(report: &BumpReport) {
+    let since = report.since.as_deref().unwrap_or("the first commit");
+    if report.reasons.is_empty() {
+        println!("No commits since {} require a version bump", since);
+        return;
+    }
+    println!("Commits since {}:", since);
+    for r in &report.reasons {
+        println!("  {}", r);
+    }
+}
+
+/// `diff` is the dry-run diff of the config file, printed only with `--output json`.
+fn print_changes(
+    changes: &[Change],
+    report: Option<&BumpReport>,
+    diff: Option<&str>,
+    silent: bool,
+    output: OutputFormat,
+) -> fuver::Result<()> {
+    if silent {
+        return Ok(());
+    }
+    match output {
+        OutputFormat::Text => {
+            if let Some(r) = report {
+                print_report(r);
+                if let (Some(part), true) = (r.bump, changes.is_empty()) {
+                    println!("The version is already bumped by {}", part);
+                }
+            }
+            changes.iter().for_each(|c| println!("{}", c));
+        }
+        OutputFormat::Json => {
+            let mut json = match report {
+                Some(r) => {
+                    let mut v = serde_json::to_value(r)?;
+                    v["changes"] = serde_json::to_value(changes)?;
+                    v
+                }
+                None if diff.is_some() => serde_json::json!({ "changes": changes }),
+                None => serde_json::to_value(changes)?,
+            };
+            if let Some(d) = diff {
+                json["diff"] = d.into();
+            }
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+    }
+    Ok(())
+}
+
+/// Current contents of `path`, or empty when it does not exist yet.
+fn read_or_empty(path: &Path) -> fuver::Result<String> {
+    match fs::read_to_string(path) {
+        Ok(s) => Ok(s),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn unified_diff(path: &Path, before: &str, after: &str) -> String {
+    let name = path.display().to_string();
+    diff::unified(before, after, &name, &format!("{} (dry-run)", name))
+}
+
+fn print_diff(path: &Path, before: &str, after: &str) {
+    let d = unified_diff(path, before, after);
+    if d.is_empty() {
+        println!("No changes to {}", path.display());
+    } else {
+        print!("{}", d);
     }
 }
 
 pub fn main() -> fuver::Result<()> {
     let args = Args::parse();
-    let conf_path = args.config.as_ref().unwrap();
 
-    if let Commands::Init { file } = args.cmd {
-        return run_init(&file);
+    match args.cmd {
+        Commands::Init {
+            file,
+            from_git,
+            prefix,
+        } => return run_init(&file, from_git.then_some(prefix.as_str()), args.dry_run),
+        Commands::Version => {
+            println!("fuver version {}", VERSION);
+            return Ok(());
+        }
+        _ => {}
+    };
+
+    let cwd = env::current_dir()?;
+    let conf_path = config::resolve(args.config.as_deref(), &cwd, DEFAULT_FILE)
+        .ok_or_else(|| FuVerError::ConfigNotFound(cwd.join(DEFAULT_FILE)))?;
+    if let Commands::Which = args.cmd {
+        println!("{}", conf_path.display());
+        return Ok(());
+    }
+
+    // 読み込みから保存までを他のプロセスと排他する
+    let _lock = match args.cmd {
+        Commands::Show { .. } | Commands::Next { .. } | Commands::Verify { .. } => None,
+        _ if args.dry_run => None,
+        _ => Some(ConfigLock::acquire(&conf_path, args.lock_timeout)?),
     };
 
-    let file_str = fs::read_to_string(conf_path).map_err(FuVerError::IO)?;
-    let mut fv = FuVer::from_str(&file_str)?;
+    let storage = storage::for_path(&conf_path);
+    let mut fv = storage.load(&conf_path)?;
 
-    match args.cmd {
-        Commands::Increment { silent, target } => run_increment(&mut fv, target, silent),
-        Commands::Set { silent, target } => run_set(&mut fv, target, silent),
+    let mut report = None;
+    let (changes, silent) = match args.cmd {
+        Commands::Increment { silent, target } => (run_increment(&mut fv, target)?, silent),
+        Commands::Set { silent, target } => (run_set(&mut fv, target)?, silent),
+        Commands::Unset { silent, target } => (run_unset(&mut fv, target)?, silent),
+        Commands::Bump { silent, .. } => {
+            let (changes, r) = run_bump(&mut fv, &conf_path)?;
+            report = Some(r);
+            (changes, silent)
+        }
+        Commands::Release {
+            silent,
+            build,
+            next,
+            pre,
+        } => {
+            let changes = fv.release(next.map(VersionPart::from), pre.as_deref(), build)?;
+            (changes, silent)
+        }
         Commands::Show { target } => {
-            match target {
-                Some(cmd) => run_show(&fv, cmd),
-                None => fv.show_version(),
-            }?;
-            Ok(())
+            let s = match (target, args.output) {
+                (None | Some(ShowCommands::Full), OutputFormat::Json) => {
+                    serde_json::to_string_pretty(&VersionInfo::new(&fv)?)?
+                }
+                (Some(cmd), OutputFormat::Json) => {
+                    // 未定義のときはテキストと同じエラーにする
+                    let key = info_key(&cmd);
+                    let shown = run_show(&fv, cmd)?;
+                    let json = match key {
+                        Some(k) => {
+                            let info = serde_json::to_value(VersionInfo::new(&fv)?)?;
+                            serde_json::json!({ k: info[k] })
+                        }
+                        None => serde_json::json!({ "build": shown }),
+                    };
+                    serde_json::to_string_pretty(&json)?
+                }
+                (Some(cmd), OutputFormat::Text) => run_show(&fv, cmd)?,
+                (None, OutputFormat::Text) => fv.get_version().to_string(),
+            };
+            println!("{}", s);
+            return Ok(());
         }
-        Commands::Version => {
-            println!("fuver version {}", VERSION);
-            Ok(())
+        Commands::Next { target } => {
+            let mut next = fv.clone();
+            run_increment(&mut next, target)?;
+            match args.output {
+                OutputFormat::Text => println!("{}", next.full_string()?),
+                OutputFormat::Json => {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&VersionInfo::new(&next)?)?
+                    )
+                }
+            }
+            return Ok(());
         }
-        _ => Ok(()),
-    }?;
-    fv.save(conf_path);
+        Commands::Sync { silent, check } => {
+            let base = conf_path.parent().unwrap_or(Path::new("."));
+            if args.dry_run {
+                for edit in fv.get_sync()?.plan(base, &fv.core_string())? {
+                    if !silent {
+                        edit.results.iter().for_each(|r| println!("{}", r));
+                    }
+                    print_diff(&edit.path, &edit.before, &edit.after);
+                }
+                return Ok(());
+            }
+            let results = fv.get_sync()?.sync(base, &fv.core_string(), check)?;
+            if !silent {
+                for r in results {
+                    println!("{}", r);
+                }
+            }
+            return Ok(());
+        }
+        Commands::Changelog { silent } => {
+            let edit = run_changelog(&fv, &conf_path)?;
+            if !args.dry_run {
+                fuver::write_atomic(&edit.path, edit.after.as_bytes())?;
+            }
+            match args.output {
+                OutputFormat::Text => {
+                    if !silent {
+                        println!("Prepend {} to {}", edit.heading, edit.path.display());
+                    }
+                    if args.dry_run {
+                        print_diff(&edit.path, &edit.before, &edit.after);
+                    }
+                }
+                OutputFormat::Json if !silent => {
+                    let mut json = serde_json::json!({
+                        "file": edit.path,
+                        "heading": edit.heading,
+                        "section": edit.section,
+                    });
+                    if args.dry_run {
+                        json["diff"] = unified_diff(&edit.path, &edit.before, &edit.after).into();
+                    }
+                    println!("{}", serde_json::to_string_pretty(&json)?);
+                }
+                OutputFormat::Json => {}
+            }
+            return Ok(());
+        }
+        Commands::Tag { silent, force } => {
+            let repo = git::open(conf_path.parent().unwrap_or(Path::new(".")))?;
+            let plan = git::plan_tag(&repo, &fv.get_git(), &fv, force)?;
+            if !args.dry_run {
+                git::create_tag(&repo, &plan)?;
+            }
+            match args.output {
+                OutputFormat::Text => {
+                    if !silent {
+                        println!("Tag {} at {}", plan.name, &plan.commit.to_string()[..8]);
+                    }
+                    if args.dry_run {
+                        print!("{}", plan.message);
+                    }
+                }
+                OutputFormat::Json if !silent => {
+                    let json = serde_json::json!({
+                        "tag": plan.name,
+                        "commit": plan.commit.to_string(),
+                        "message": plan.message,
+                    });
+                    println!("{}", serde_json::to_string_pretty(&json)?);
+                }
+                OutputFormat::Json => {}
+            }
+            return Ok(());
+        }
+        Commands::Verify { silent, tag } => {
+            let repo = git::open(conf_path.parent().unwrap_or(Path::new(".")))?;
+            let verified = verify::verify(&repo, &fv.get_git(), &conf_path, &fv, tag.as_deref())?;
+            if !silent {
+                match args.output {
+                    OutputFormat::Text => println!("{}", verified),
+                    OutputFormat::Json => {
+                        println!("{}", serde_json::to_string_pretty(&verified)?)
+                    }
+                }
+            }
+            return Ok(());
+        }
+        Commands::Init { .. } | Commands::Version | Commands::Which => return Ok(()),
+    };
+    if args.dry_run {
+        let before = read_or_empty(&conf_path)?;
+        let after = storage.render(&fv, &conf_path)?;
+        if args.output == OutputFormat::Json {
+            let diff = unified_diff(&conf_path, &before, &after);
+            return print_changes(&changes, report.as_ref(), Some(&diff), silent, args.output);
+        }
+        print_changes(&changes, report.as_ref(), None, silent, args.output)?;
+        println!("version {}", &fv);
+        print_diff(&conf_path, &before, &after);
+        return Ok(());
+    }
+    if !changes.is_empty() {
+        storage.save(&fv, &conf_path)?;
+    }
+    print_changes(&changes, report.as_ref(), None, silent, args.output)?;
     Ok(())
 }
@@ -0,0 +1,99 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable to specify the config file.
+pub const CONFIG_ENV: &str = "FUVER_CONFIG";
+
+/// Find `name` in `start` or its parent directories.
+///
+/// Like git, the search stops at the repository root (a directory containing `.git`).
+pub fn discover(start: &Path, name: &str) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let candidate = dir.join(name);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Resolve the config path.
+///
+/// In order of priority:
+/// 1. `explicit` (`--config`)
+/// 2. the `FUVER_CONFIG` environment variable
+/// 3. `name` found by [`discover`] from `cwd`
+///
+/// Relative paths are resolved from `cwd`.
+pub fn resolve(explicit: Option<&str>, cwd: &Path, name: &str) -> Option<PathBuf> {
+    let specified = explicit.map(PathBuf::from).or_else(|| {
+        env::var_os(CONFIG_ENV)
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+    });
+    match specified {
+        Some(p) => Some(cwd.join(p)),
+        None => discover(cwd, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, process};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("fuver-config-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_discover() {
+        let root = temp_dir("discover");
+        let sub = root.join("crates/sub/src");
+        fs::create_dir_all(&sub).unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join("fuver.toml"), "").unwrap();
+
+        assert_eq!(discover(&sub, "fuver.toml"), Some(root.join("fuver.toml")));
+        assert_eq!(discover(&root, "fuver.toml"), Some(root.join("fuver.toml")));
+
+        fs::write(root.join("crates/sub/fuver.toml"), "").unwrap();
+        assert_eq!(
+            discover(&sub, "fuver.toml"),
+            Some(root.join("crates/sub/fuver.toml"))
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_discover_stops_at_repository_root() {
+        let outer = temp_dir("root");
+        let repo = outer.join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::write(outer.join("fuver.toml"), "").unwrap();
+
+        assert_eq!(discover(&repo, "fuver.toml"), None);
+        fs::remove_dir_all(&outer).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_explicit() {
+        let cwd = Path::new("/work/src");
+        assert_eq!(
+            resolve(Some("../fuver.toml"), cwd, "fuver.toml"),
+            Some(PathBuf::from("/work/src/../fuver.toml"))
+        );
+        assert_eq!(
+            resolve(Some("/etc/fuver.toml"), cwd, "fuver.toml"),
+            Some(PathBuf::from("/etc/fuver.toml"))
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::git::CommitInfo;
+use crate::version::VersionPart;
+
+/// Header and breaking change of a commit message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    /// `feat`, `fix`, ...
+    pub kind: String,
+    pub scope: Option<String>,
+    /// `!` in the header or a `BREAKING CHANGE:` footer
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    /// Parse `type(scope)!: description`. `None` when the message does not follow it.
+    pub fn parse(message: &str) -> Option<ConventionalCommit> {
+        let header = message.lines().next()?;
+        let (prefix, description) = header.split_once(": ")?;
+        let (prefix, bang) = match prefix.strip_suffix('!') {
+            Some(p) => (p, true),
+            None => (prefix, false),
+        };
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((k, rest)) => (k, Some(rest.strip_suffix(')')?.to_string())),
+            None => (prefix, None),
+        };
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        let footer_breaking = message
+            .lines()
+            .skip(1)
+            .any(|l| l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:"));
+        Some(ConventionalCommit {
+            kind: kind.to_ascii_lowercase(),
+            scope,
+            breaking: bang || footer_breaking,
+            description: description.trim().to_string(),
+        })
+    }
+
+    /// `major` for breaking changes, `minor` for `feat`, `patch` for `fix` and `perf`.
+    pub fn bump(&self) -> Option<VersionPart> {
+        if self.breaking {
+            return Some(VersionPart::Major);
+        }
+        match self.kind.as_str() {
+            "feat" => Some(VersionPart::Minor),
+            "fix" | "perf" => Some(VersionPart::Patch),
+            _ => None,
+        }
+    }
+}
+
+/// Commit that triggered a bump
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BumpReason {
+    pub id: String,
+    pub summary: String,
+    pub bump: VersionPart,
+}
+
+impl fmt::Display for BumpReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let short = self.id.get(..8).unwrap_or(&self.id);
+        write!(f, "{} {} => {}", short, self.summary, self.bump)
+    }
+}
+
+/// Result of [`analyze`]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BumpReport {
+    /// tag the commits were collected from. `None` means the whole history.
+    pub since: Option<String>,
+    pub reasons: Vec<BumpReason>,
+    /// the highest part of `reasons`
+    pub bump: Option<VersionPart>,
+}
+
+/// Decide the bump from `commits`.
+pub fn analyze(since: Option<String>, commits: &[CommitInfo]) -> BumpReport {
+    let reasons: Vec<BumpReason> = commits
+        .iter()
+        .filter_map(|c| {
+            let bump = ConventionalCommit::parse(&c.message)?.bump()?;
+            Some(BumpReason {
+                id: c.id.clone(),
+                summary: c.summary.clone(),
+                bump,
+            })
+        })
+        .collect();
+    let bump = reasons.iter().map(|r| r.bump).min();
+    BumpReport {
+        since,
+        reasons,
+        bump,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let c = ConventionalCommit::parse("feat(cli): add bump command\n\nbody").unwrap();
+        assert_eq!(c.kind, "feat");
+        assert_eq!(c.scope.as_deref(), Some("cli"));
+        assert!(!c.breaking);
+        assert_eq!(c.description, "add bump command");
+
+        assert!(
+            ConventionalCommit::parse("fix!: drop old format")
+                .unwrap()
+                .breaking
+        );
+        assert!(
+            ConventionalCommit::parse("refactor(api)!: rename")
+                .unwrap()
+                .breaking
+        );
+        assert!(
+            ConventionalCommit::parse("feat: x\n\nBREAKING CHANGE: removed y")
+                .unwrap()
+                .breaking
+        );
+
+        assert_eq!(ConventionalCommit::parse("Merge branch 'main'"), None);
+        assert_eq!(ConventionalCommit::parse("update readme: typo"), None);
+        assert_eq!(ConventionalCommit::parse("feat(cli: broken"), None);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let commit = |id: &str, message: &str| CommitInfo {
+            id: id.to_string(),
+            summary: message.lines().next().unwrap().to_string(),
+            message: message.to_string(),
+            time: 0,
+        };
+        let commits = [
+            commit("1111111111", "docs: readme"),
+            commit("2222222222", "fix: crash on empty config"),
+            commit("3333333333", "feat: add json output"),
+        ];
+        let report = analyze(Some("v1.0.0".to_string()), &commits);
+        assert_eq!(report.bump, Some(VersionPart::Minor));
+        assert_eq!(report.reasons.len(), 2);
+        assert_eq!(
+            report.reasons[1].to_string(),
+            "33333333 feat: add json output => minor"
+        );
+
+        let report = analyze(None, &[commit("4", "perf!: new storage\n")]);
+        assert_eq!(report.bump, Some(VersionPart::Major));
+        assert_eq!(analyze(None, &[commit("5", "chore: deps")]).bump, None);
+    }
+}
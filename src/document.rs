@@ -0,0 +1,269 @@
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value};
+
+/// Update `old` with the values of `new`, keeping comments and formatting of `old`.
+///
+/// Only changed values are replaced and their decoration (comments, spaces) is kept.
+/// Keys missing in `new` are removed, and new keys are appended.
+pub fn merge(old: &mut DocumentMut, new: &DocumentMut) {
+    let mut position = max_position(old.as_table()) + 1;
+    merge_table(old.as_table_mut(), new.as_table(), &mut position);
+}
+
+/// Same as [`merge`] for the table at `path`, which is created when missing.
+///
+/// Returns `false` when an item on `path` is not a table.
+pub fn merge_at(old: &mut DocumentMut, path: &[&str], new: &Table) -> bool {
+    let mut position = max_position(old.as_table()) + 1;
+    match table_at(old.as_table_mut(), path) {
+        Some(t) => {
+            merge_table(t, new, &mut position);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Set the string at `path` keeping its decoration.
+///
+/// Returns `false` when the item is not a string (e.g. `version.workspace = true`)
+/// or its parent is not a table.
+pub fn set_string(doc: &mut DocumentMut, path: &[&str], s: &str) -> bool {
+    let Some((key, parent)) = path.split_last() else {
+        return false;
+    };
+    let Some(table) = table_at(doc.as_table_mut(), parent) else {
+        return false;
+    };
+    match table.get_mut(key) {
+        Some(Item::Value(v @ Value::String(_))) => {
+            let decor = v.decor().clone();
+            *v = Value::from(s);
+            *v.decor_mut() = decor;
+            true
+        }
+        Some(_) => false,
+        None => {
+            table.insert(key, toml_edit::value(s));
+            true
+        }
+    }
+}
+
+/// Get the table at `path`, creating missing tables as implicit ones.
+fn table_at<'a>(root: &'a mut Table, path: &[&str]) -> Option<&'a mut dyn TableLike> {
+    let mut current: &mut dyn TableLike = root;
+    for key in path {
+        if !current.contains_key(key) {
+            let mut t = Table::new();
+            t.set_implicit(true);
+            current.insert(key, Item::Table(t));
+        }
+        current = current.get_mut(key)?.as_table_like_mut()?;
+    }
+    Some(current)
+}
+
+fn merge_table(old: &mut dyn TableLike, new: &dyn TableLike, position: &mut usize) {
+    let removed: Vec<String> = old
+        .iter()
+        .filter(|(k, _)| !new.contains_key(k))
+        .map(|(k, _)| k.to_string())
+        .collect();
+    for k in removed {
+        old.remove(&k);
+    }
+
+    for (key, new_item) in new.iter() {
+        match old.get_mut(key) {
+            Some(old_item) if old_item.is_table_like() && new_item.is_table_like() => {
+                if let (Some(o), Some(n)) = (old_item.as_table_like_mut(), new_item.as_table_like())
+                {
+                    merge_table(o, n, position);
+                }
+            }
+            Some(Item::ArrayOfTables(old_tables))
+                if new_item
+                    .as_array_of_tables()
+                    .is_some_and(|n| n.len() == old_tables.len()) =>
+            {
+                if let Some(n) = new_item.as_array_of_tables() {
+                    for (o, n) in old_tables.iter_mut().zip(n.iter()) {
+                        merge_table(o, n, position);
+                    }
+                }
+            }
+            Some(Item::Value(old_value)) => {
+                // `[[a]]` of the serializer is kept inline when written inline
+                let new_value = match new_item {
+                    Item::Value(v) => Some(v.clone()),
+                    Item::ArrayOfTables(a) => Some(Value::Array(a.clone().into_array())),
+                    _ => None,
+                };
+                match new_value {
+                    Some(v) if same_value(old_value, &v) => {}
+                    Some(v) => {
+                        let decor = old_value.decor().clone();
+                        *old_value = v;
+                        *old_value.decor_mut() = decor;
+                    }
+                    None => {
+                        old.insert(key, renumber(new_item, position));
+                    }
+                }
+            }
+            _ => {
+                old.insert(key, renumber(new_item, position));
+            }
+        }
+    }
+}
+
+/// Tables copied from another document get positions after the existing ones,
+/// separated by a blank line.
+fn renumber(item: &Item, position: &mut usize) -> Item {
+    let mut item = item.clone();
+    match &mut item {
+        Item::Table(t) => renumber_table(t, position),
+        Item::ArrayOfTables(a) => a.iter_mut().for_each(|t| renumber_table(t, position)),
+        _ => {}
+    }
+    item
+}
+
+fn renumber_table(table: &mut Table, position: &mut usize) {
+    table.set_position(*position);
+    table.decor_mut().set_prefix("\n");
+    *position += 1;
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(t) => renumber_table(t, position),
+            Item::ArrayOfTables(a) => a.iter_mut().for_each(|t| renumber_table(t, position)),
+            _ => {}
+        }
+    }
+}
+
+fn max_position(table: &Table) -> usize {
+    table
+        .iter()
+        .flat_map(|(_, item)| match item {
+            Item::Table(t) => vec![t],
+            Item::ArrayOfTables(a) => a.iter().collect(),
+            _ => vec![],
+        })
+        .map(|t| t.position().unwrap_or(0).max(max_position(t)))
+        .max()
+        .unwrap_or(0)
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(x), Value::String(y)) => x.value() == y.value(),
+        (Value::Integer(x), Value::Integer(y)) => x.value() == y.value(),
+        (Value::Float(x), Value::Float(y)) => x.value() == y.value(),
+        (Value::Boolean(x), Value::Boolean(y)) => x.value() == y.value(),
+        (Value::Datetime(x), Value::Datetime(y)) => x.value() == y.value(),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y.iter()).all(|(a, b)| same_value(a, b))
+        }
+        (Value::InlineTable(x), Value::InlineTable(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|w| same_value(v, w)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(old: &str, new: &str) -> String {
+        let mut old: DocumentMut = old.parse().unwrap();
+        let new: DocumentMut = new.parse().unwrap();
+        merge(&mut old, &new);
+        old.to_string()
+    }
+
+    #[test]
+    fn test_merge_keeps_comments() {
+        let old = r#"# バージョン情報
+[version]
+major = 0 # 破壊的変更
+minor   = 11
+patch = 6
+"#;
+        let new = "[version]\nmajor = 0\nminor = 12\npatch = 0\n";
+        assert_eq!(
+            merged(old, new),
+            r#"# バージョン情報
+[version]
+major = 0 # 破壊的変更
+minor   = 12
+patch = 0
+"#
+        );
+    }
+
+    #[test]
+    fn test_merge_add_and_remove() {
+        let old = "[version]\nmajor = 1\n\n# プレリリース\n[pre]\ntag = \"rc\"\nnumber = 1\n";
+        let new = "[version]\nmajor = 1\n\n[build]\nnumber = 3\n";
+        assert_eq!(
+            merged(old, new),
+            "[version]\nmajor = 1\n\n[build]\nnumber = 3\n"
+        );
+
+        let old = "[version]\nmajor = 1\n\n[build]\nnumber = 3\n";
+        let new = "[version]\nmajor = 1\n\n[pre]\ntag = \"rc\"\n\n[build]\nnumber = 3\n";
+        assert_eq!(
+            merged(old, new),
+            "[version]\nmajor = 1\n\n[build]\nnumber = 3\n\n[pre]\ntag = \"rc\"\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_at() {
+        let mut doc: DocumentMut = "[package]\nname = \"a\" # 名前\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1\"\n"
+            .parse()
+            .unwrap();
+        let new: DocumentMut = "[version]\nmajor = 1\n".parse().unwrap();
+        assert!(merge_at(
+            &mut doc,
+            &["package", "metadata", "fuver"],
+            new.as_table()
+        ));
+        assert!(set_string(&mut doc, &["package", "version"], "1.0.0"));
+        assert_eq!(
+            doc.to_string(),
+            "[package]\nname = \"a\" # 名前\nversion = \"1.0.0\"\n\n[dependencies]\nserde = \"1\"\n\n[package.metadata.fuver.version]\nmajor = 1\n"
+        );
+
+        let mut doc: DocumentMut = "[package]\nversion.workspace = true\n".parse().unwrap();
+        assert!(!set_string(&mut doc, &["package", "version"], "1.0.0"));
+    }
+
+    #[test]
+    fn test_merge_array_of_tables() {
+        let old = "[[sync]]\nfile = \"a\" # 対象\n\n[[sync]]\nfile = \"b\"\n";
+        let new = "[[sync]]\nfile = \"a\"\n\n[[sync]]\nfile = \"c\"\n";
+        assert_eq!(
+            merged(old, new),
+            "[[sync]]\nfile = \"a\" # 対象\n\n[[sync]]\nfile = \"c\"\n"
+        );
+
+        let old = "sync = [{ file = \"a\" }] # inline\n";
+        assert_eq!(merged(old, "[[sync]]\nfile = \"a\"\n"), old);
+    }
+
+    #[test]
+    fn test_merge_inline_table() {
+        let old = "version = { major = 1, minor = 2 } # inline\n";
+        let new = "[version]\nmajor = 1\nminor = 3\n";
+        assert_eq!(
+            merged(old, new),
+            "version = { major = 1, minor = 3 } # inline\n"
+        );
+    }
+}
@@ -1,13 +1,24 @@
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::error;
 use std::fmt;
 use std::fmt::Display;
-use std::fs;
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::result;
 use std::str::FromStr;
 
 use crate::buildmeta;
+use crate::changelog;
+use crate::document;
+use crate::git;
+use crate::identifier;
+use crate::lock;
 use crate::pre;
+use crate::sync;
+use crate::verify;
 use crate::version;
 
 pub type Result<T> = result::Result<T, FuVerError>;
@@ -15,11 +26,35 @@ pub type Result<T> = result::Result<T, FuVerError>;
 pub enum FuVerError {
     IO(io::Error),
     InitError(String),
+    ConfigNotFound(PathBuf),
+    Manifest(String),
     Deserialize(toml::de::Error),
+    Serialize(toml::ser::Error),
+    Json(serde_json::Error),
+    Document(toml_edit::TomlError),
     Version(version::VersionError),
-    Error(String),
+    PreRelease(pre::PreReleaseError),
+    BuildMeta(buildmeta::BuildMetaError),
+    Parse(ParseError),
+    Lock(lock::LockError),
+    Sync(sync::SyncError),
+    Changelog(changelog::ChangelogError),
+    Git(git2::Error),
+    Tag(git::TagError),
+    Verify(verify::VerifyError),
     PreReleaseNotDefined,
     BuildMetaDataNotDefined,
+    SyncNotDefined,
+}
+
+impl FuVerError {
+    /// Process exit code. see [`verify::VerifyError::exit_code`]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FuVerError::Verify(e) => e.exit_code(),
+            _ => 1,
+        }
+    }
 }
 
 impl fmt::Display for FuVerError {
@@ -27,24 +62,227 @@ impl fmt::Display for FuVerError {
         match self {
             FuVerError::IO(e) => write!(f, "I/O Error: {}", e),
             FuVerError::Deserialize(e) => write!(f, "Desirialize Error: {}", e),
+            FuVerError::Serialize(e) => write!(f, "Serialize Error: {}", e),
+            FuVerError::Json(e) => write!(f, "JSON Error: {}", e),
+            FuVerError::Document(e) => write!(f, "Document Error: {}", e),
             FuVerError::Version(e) => write!(f, "Version Error: {}", e),
-            FuVerError::Error(e) => write!(f, "Error: {}", e),
+            FuVerError::PreRelease(e) => write!(f, "Pre-Release Error: {}", e),
+            FuVerError::BuildMeta(e) => write!(f, "BuildMetaData Error: {}", e),
+            FuVerError::Parse(e) => write!(f, "Parse Error: {}", e),
+            FuVerError::Lock(e) => write!(f, "Lock Error: {}", e),
+            FuVerError::Sync(e) => write!(f, "Sync Error: {}", e),
+            FuVerError::Changelog(e) => write!(f, "Changelog Error: {}", e),
+            FuVerError::Git(e) => write!(f, "Git Error: {}", e),
+            FuVerError::Tag(e) => write!(f, "Tag Error: {}", e),
+            FuVerError::Verify(e) => write!(f, "Verify Error: {}", e),
             FuVerError::InitError(e) => write!(f, "Initialize Error: {}", e),
+            FuVerError::Manifest(e) => write!(f, "Manifest Error: {}", e),
+            FuVerError::ConfigNotFound(p) => write!(
+                f,
+                "Config Not Found: {} (or set --config, FUVER_CONFIG)",
+                p.display()
+            ),
             FuVerError::PreReleaseNotDefined => write!(f, "Pre-Release is Not Defined."),
             FuVerError::BuildMetaDataNotDefined => write!(f, "BuildMetaData is Not Defined."),
+            FuVerError::SyncNotDefined => write!(f, "Sync is Not Defined."),
+        }
+    }
+}
+
+impl error::Error for FuVerError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FuVerError::IO(e) => Some(e),
+            FuVerError::Deserialize(e) => Some(e),
+            FuVerError::Serialize(e) => Some(e),
+            FuVerError::Json(e) => Some(e),
+            FuVerError::Document(e) => Some(e),
+            FuVerError::Version(e) => Some(e),
+            FuVerError::PreRelease(e) => Some(e),
+            FuVerError::BuildMeta(e) => Some(e),
+            FuVerError::Parse(e) => Some(e),
+            FuVerError::Lock(e) => Some(e),
+            FuVerError::Sync(e) => Some(e),
+            FuVerError::Changelog(e) => Some(e),
+            FuVerError::Git(e) => Some(e),
+            FuVerError::Tag(e) => Some(e),
+            FuVerError::Verify(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FuVerError {
+    fn from(e: io::Error) -> Self {
+        FuVerError::IO(e)
+    }
+}
+
+impl From<toml::de::Error> for FuVerError {
+    fn from(e: toml::de::Error) -> Self {
+        FuVerError::Deserialize(e)
+    }
+}
+
+impl From<toml::ser::Error> for FuVerError {
+    fn from(e: toml::ser::Error) -> Self {
+        FuVerError::Serialize(e)
+    }
+}
+
+impl From<serde_json::Error> for FuVerError {
+    fn from(e: serde_json::Error) -> Self {
+        FuVerError::Json(e)
+    }
+}
+
+impl From<toml_edit::TomlError> for FuVerError {
+    fn from(e: toml_edit::TomlError) -> Self {
+        FuVerError::Document(e)
+    }
+}
+
+impl From<version::VersionError> for FuVerError {
+    fn from(e: version::VersionError) -> Self {
+        FuVerError::Version(e)
+    }
+}
+
+impl From<pre::PreReleaseError> for FuVerError {
+    fn from(e: pre::PreReleaseError) -> Self {
+        FuVerError::PreRelease(e)
+    }
+}
+
+impl From<buildmeta::BuildMetaError> for FuVerError {
+    fn from(e: buildmeta::BuildMetaError) -> Self {
+        FuVerError::BuildMeta(e)
+    }
+}
+
+impl From<lock::LockError> for FuVerError {
+    fn from(e: lock::LockError) -> Self {
+        FuVerError::Lock(e)
+    }
+}
+
+impl From<sync::SyncError> for FuVerError {
+    fn from(e: sync::SyncError) -> Self {
+        FuVerError::Sync(e)
+    }
+}
+
+impl From<changelog::ChangelogError> for FuVerError {
+    fn from(e: changelog::ChangelogError) -> Self {
+        FuVerError::Changelog(e)
+    }
+}
+
+impl From<git2::Error> for FuVerError {
+    fn from(e: git2::Error) -> Self {
+        FuVerError::Git(e)
+    }
+}
+
+impl From<git::TagError> for FuVerError {
+    fn from(e: git::TagError) -> Self {
+        FuVerError::Tag(e)
+    }
+}
+
+impl From<verify::VerifyError> for FuVerError {
+    fn from(e: verify::VerifyError) -> Self {
+        FuVerError::Verify(e)
+    }
+}
+
+impl From<ParseError> for FuVerError {
+    fn from(e: ParseError) -> Self {
+        FuVerError::Parse(e)
+    }
+}
+
+/// Error while parsing a SemVer string
+///
+/// `position` is the byte offset in `input` where the invalid part starts.
+#[derive(Debug)]
+pub struct ParseError {
+    pub input: String,
+    pub position: usize,
+    pub reason: String,
+}
+
+impl error::Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at position {} in \"{}\"",
+            self.reason, self.position, self.input
+        )
+    }
+}
+
+/// Record of a change made by a `FuVer` mutator
+///
+/// Serialized as `{"field", "action", "old", "new"}` for `--output json`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// changed field (e.g. `version`, `pre`, `build`)
+    pub field: &'static str,
+    /// operation (e.g. `Increment Patch Version`)
+    pub action: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl Change {
+    pub fn new(field: &'static str, action: &'static str, old: String, new: String) -> Self {
+        Self {
+            field,
+            action,
+            old,
+            new,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} -> {}", self.action, self.old, self.new)
+    }
+}
+
+/// Ordering follows SemVer 2.0 precedence.
+///
+/// A version with pre-release has lower precedence than the release version,
+/// and build metadata is ignored for both ordering and equality.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct FuVer {
+    /// Ordered pre-release stages. [`pre::DEFAULT_STAGES`] is used when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stages: Option<Vec<String>>,
+
+    /// Release tags. see [`git::GitConfig`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    git: Option<git::GitConfig>,
+
+    /// Files to copy the version to. see [`sync::SyncConfig`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sync: Option<sync::SyncConfig>,
+
+    /// `fuver changelog` template. see [`changelog::ChangelogConfig`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    changelog: Option<changelog::ChangelogConfig>,
+
     #[serde(default)]
     version: version::Version,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pre: Option<pre::PreRelease>,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     build: Option<buildmeta::BuildMetaData>,
 }
 
@@ -61,334 +299,1228 @@ impl fmt::Display for FuVer {
     }
 }
 
+impl PartialEq for FuVer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FuVer {}
+
+impl PartialOrd for FuVer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FuVer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.version
+            .cmp(&other.version)
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+    }
+}
+
 impl FromStr for FuVer {
     type Err = FuVerError;
     fn from_str(s: &str) -> result::Result<Self, Self::Err> {
-        let fv = toml::from_str(s).map_err(FuVerError::Deserialize)?;
+        let fv = toml::from_str(s)?;
         Ok(fv)
     }
 }
 
 impl FuVer {
-    pub fn save(&self, p: &str) {
-        fs::write(p, toml::to_string(&self).unwrap()).unwrap();
+    /// Parse a full SemVer string such as `1.2.3-rc.1+build.5`.
+    ///
+    /// Every part is checked with the SemVer identifier rules and the error
+    /// reports the position where parsing failed.
+    pub fn parse(s: &str) -> Result<FuVer> {
+        let err = |position: usize, reason: String| {
+            FuVerError::Parse(ParseError {
+                input: s.to_string(),
+                position,
+                reason,
+            })
+        };
+
+        let (rest, build) = match s.split_once('+') {
+            Some((r, b)) => (r, Some(b)),
+            None => (s, None),
+        };
+        let (core, pre) = match rest.split_once('-') {
+            Some((c, p)) => (c, Some(p)),
+            None => (rest, None),
+        };
+
+        let version = parse_core(core).map_err(|(pos, reason)| err(pos, reason))?;
+
+        let pre = match pre {
+            Some(p) => {
+                let offset = core.len() + 1;
+                Some(parse_pre(p).map_err(|(pos, reason)| err(offset + pos, reason))?)
+            }
+            None => None,
+        };
+
+        let build = match build {
+            Some(b) => {
+                let offset = rest.len() + 1;
+                check_identifiers(b, identifier::check_identifier)
+                    .map_err(|(pos, reason)| err(offset + pos, reason))?;
+                let meta = buildmeta::BuildMetaData::from_str(b)
+                    .map_err(|e| err(offset, e.to_string()))?;
+                Some(meta)
+            }
+            None => None,
+        };
+
+        Ok(FuVer {
+            stages: None,
+            git: None,
+            sync: None,
+            changelog: None,
+            version,
+            pre,
+            build,
+        })
     }
 
-    pub fn incr_ver_major(&mut self, silent: bool) -> Result<()> {
-        Self::set_helper(
-            &mut self.version,
-            |v| v.increment_major().map_err(FuVerError::Version),
+    /// Version and pre-release of `release` with `build_number` as the build number
+    /// and `hash` as the build hash.
+    ///
+    /// Used to start from an existing release tag.
+    pub fn from_release(release: &FuVer, build_number: usize, hash: &str) -> Result<FuVer> {
+        let mut build = buildmeta::BuildMetaData::default();
+        build.set_number(build_number)?;
+        build.set_hash(hash)?;
+        Ok(FuVer {
+            version: release.version.clone(),
+            pre: release.pre.clone(),
+            build: Some(build),
+            ..Default::default()
+        })
+    }
+
+    /// Save to `p`.
+    ///
+    /// If `p` exists, only the changed values are updated and comments and
+    /// formatting are kept.
+    /// The file is written to a temporary file and renamed, so `p` is never
+    /// left truncated. The permissions of the existing file are kept.
+    ///
+    /// # Errors
+    /// * serialization failed
+    /// * I/O error, or `p` is read-only
+    pub fn save<P: AsRef<Path>>(&self, p: P) -> Result<()> {
+        let p = p.as_ref();
+        let s = self.render(p)?;
+        write_atomic(p, s.as_bytes())?;
+        Ok(())
+    }
+
+    /// Contents [`FuVer::save`] would write to `p`.
+    ///
+    /// # Errors
+    /// `p` exists and is not a valid TOML document
+    pub fn render<P: AsRef<Path>>(&self, p: P) -> Result<String> {
+        let new: toml_edit::DocumentMut = toml::to_string(&self)?.parse()?;
+        let doc = match fs::read_to_string(p) {
+            Ok(s) => {
+                let mut old: toml_edit::DocumentMut = s.parse()?;
+                document::merge(&mut old, &new);
+                old
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => new,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(doc.to_string())
+    }
+
+    /// Increment major version.
+    ///
+    /// A pending pre-release of `x.0.0` is finished instead (`1.0.0-alpha.2` -> `1.0.0`).
+    pub fn incr_ver_major(&mut self) -> Result<Change> {
+        self.update_helper(
+            |fv| fv.increment(version::VersionPart::Major),
             "Increment Major Version",
-            silent,
         )
     }
-    pub fn incr_ver_minor(&mut self, silent: bool) -> Result<()> {
-        Self::set_helper(
-            &mut self.version,
-            |v| v.increment_minor().map_err(FuVerError::Version),
+
+    /// Increment minor version.
+    ///
+    /// A pending pre-release of `x.y.0` is finished instead (`1.2.0-alpha.2` -> `1.2.0`).
+    pub fn incr_ver_minor(&mut self) -> Result<Change> {
+        self.update_helper(
+            |fv| fv.increment(version::VersionPart::Minor),
             "Increment Minor Version",
-            silent,
         )
     }
 
-    pub fn incr_ver_patch(&mut self, silent: bool) -> Result<()> {
-        Self::set_helper(
-            &mut self.version,
-            |v| v.increment_patch().map_err(FuVerError::Version),
+    /// Increment patch version.
+    ///
+    /// A pending pre-release is finished instead (`1.2.3-alpha.2` -> `1.2.3`).
+    pub fn incr_ver_patch(&mut self) -> Result<Change> {
+        self.update_helper(
+            |fv| fv.increment(version::VersionPart::Patch),
             "Increment Patch Version",
-            silent,
         )
     }
 
-    pub fn incr_ver_mask(&mut self, mask: &str, silent: bool) -> Result<()> {
-        Self::set_helper(
-            &mut self.version,
-            |v| v.increment_mask(mask).map_err(FuVerError::Version),
+    /// Finish the pre-release when it is pending for `part`, otherwise drop it
+    /// and increment `part`.
+    fn increment(&mut self, part: version::VersionPart) -> Result<()> {
+        let v = &self.version;
+        let pending = match part {
+            version::VersionPart::Major => v.get_minor() == 0 && v.get_patch() == 0,
+            version::VersionPart::Minor => v.get_patch() == 0,
+            version::VersionPart::Patch => true,
+        };
+        if self.pre.take().is_some() && pending {
+            return Ok(());
+        }
+        self.version.increment(part).map_err(FuVerError::Version)
+    }
+
+    /// Increment `part`. see [`FuVer::incr_ver_major`], [`FuVer::incr_ver_minor`], [`FuVer::incr_ver_patch`]
+    pub fn incr_ver(&mut self, part: version::VersionPart) -> Result<Change> {
+        match part {
+            version::VersionPart::Major => self.incr_ver_major(),
+            version::VersionPart::Minor => self.incr_ver_minor(),
+            version::VersionPart::Patch => self.incr_ver_patch(),
+        }
+    }
+
+    /// Set the version and pre-release to those of `target`. Build metadata is kept.
+    pub fn bump_to(&mut self, target: &FuVer) -> Result<Change> {
+        self.update_helper(
+            |fv| {
+                fv.version = target.version.clone();
+                fv.pre = target.pre.clone();
+                Ok(())
+            },
+            "Bump Version",
+        )
+    }
+
+    /// Increment using mask.
+    ///
+    /// The pre-release is handled as in [`FuVer::incr_ver`]: finishing a pending
+    /// pre-release counts as the first increment (`1.0.0-alpha.2` with `1.x.x` -> `1.0.0`).
+    pub fn incr_ver_mask(&mut self, mask: &str) -> Result<Change> {
+        self.update_helper(
+            |fv| {
+                // マスクで上がる桁と回数
+                let mut step = version::Version::new(0, 0, 0);
+                step.increment_mask(mask).map_err(FuVerError::Version)?;
+                let (part, count) = match (step.get_major(), step.get_minor(), step.get_patch()) {
+                    (0, 0, n) => (version::VersionPart::Patch, n),
+                    (0, n, _) => (version::VersionPart::Minor, n),
+                    (n, _, _) => (version::VersionPart::Major, n),
+                };
+                for _ in 0..count {
+                    fv.increment(part)?;
+                }
+                Ok(())
+            },
             "Increment Version",
-            silent,
         )
     }
 
-    pub fn incr_pre(&mut self, silent: bool) -> Result<()> {
-        let pre = self.pre.get_or_insert_with(pre::PreRelease::default);
-        Self::set_helper(
-            pre,
-            |p| {
-                p.increment_number()
-                    .map_err(|e| FuVerError::Error(e.to_string()))
+    /// Increment major version and start a new pre-release (`1.2.3` -> `2.0.0-alpha.1`).
+    ///
+    /// `tag` defaults to the first stage.
+    pub fn incr_premajor(&mut self, tag: Option<&str>) -> Result<Change> {
+        self.update_helper(
+            |fv| fv.start_pre(version::VersionPart::Major, tag),
+            "Increment Pre-Major Version",
+        )
+    }
+
+    /// Increment minor version and start a new pre-release (`1.2.3` -> `1.3.0-alpha.1`).
+    pub fn incr_preminor(&mut self, tag: Option<&str>) -> Result<Change> {
+        self.update_helper(
+            |fv| fv.start_pre(version::VersionPart::Minor, tag),
+            "Increment Pre-Minor Version",
+        )
+    }
+
+    /// Increment patch version and start a new pre-release (`1.2.3` -> `1.2.4-alpha.1`).
+    pub fn incr_prepatch(&mut self, tag: Option<&str>) -> Result<Change> {
+        self.update_helper(
+            |fv| fv.start_pre(version::VersionPart::Patch, tag),
+            "Increment Pre-Patch Version",
+        )
+    }
+
+    /// Increment pre-release.
+    ///
+    /// * no pre-release: same as `incr_prepatch` (`1.2.3` -> `1.2.4-alpha.1`)
+    /// * same tag or no tag given: increment number (`1.2.4-alpha.1` -> `1.2.4-alpha.2`)
+    /// * other tag given: restart with the tag (`1.2.4-alpha.2` -> `1.2.4-beta.1`)
+    pub fn incr_prerelease(&mut self, tag: Option<&str>) -> Result<Change> {
+        self.update_helper(
+            |fv| match fv.pre.as_mut() {
+                None => fv.start_pre(version::VersionPart::Patch, tag),
+                Some(p) if tag.is_none_or(|t| t == p.get_tag()) => {
+                    p.increment_number().map_err(FuVerError::PreRelease)
+                }
+                Some(_) => {
+                    fv.pre = Some(fv.new_pre(tag)?);
+                    Ok(())
+                }
             },
             "Increment Pre-Release",
-            silent,
         )
     }
 
-    pub fn incr_build_num(&mut self, silent: bool) -> Result<()> {
+    pub fn incr_pre(&mut self) -> Result<Change> {
+        self.incr_prerelease(None)
+    }
+
+    fn stages(&self) -> Vec<String> {
+        match self.stages.as_ref() {
+            Some(s) => s.clone(),
+            None => pre::DEFAULT_STAGES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// `<tag>.1` with `tag` or the first stage
+    fn new_pre(&self, tag: Option<&str>) -> Result<pre::PreRelease> {
+        let first = self.stages().into_iter().next();
+        let tag = tag
+            .map(|t| t.to_string())
+            .or(first)
+            .unwrap_or_else(|| pre::PreRelease::default().get_tag());
+        pre::PreRelease::with_number(&tag, 1).map_err(FuVerError::PreRelease)
+    }
+
+    fn start_pre(&mut self, part: version::VersionPart, tag: Option<&str>) -> Result<()> {
+        let pre = self.new_pre(tag)?;
+        self.version.increment(part).map_err(FuVerError::Version)?;
+        self.pre = Some(pre);
+        Ok(())
+    }
+
+    /// Move pre-release to the next stage (e.g. `alpha.3` -> `beta.1`).
+    ///
+    /// If the pre-release is already the last stage, it is released when
+    /// `release` is set. Otherwise it is an error.
+    pub fn incr_pre_stage(&mut self, release: bool) -> Result<Vec<Change>> {
+        let stages = self.stages();
+        let pre = self.pre.as_ref().ok_or(FuVerError::PreReleaseNotDefined)?;
+        if release && stages.last().is_some_and(|s| *s == pre.get_tag()) {
+            return self.release(None, None, false);
+        }
+
+        let pre = self.pre.as_mut().ok_or(FuVerError::PreReleaseNotDefined)?;
+        let change = Self::set_helper(
+            pre,
+            |p| p.next_stage(&stages).map_err(FuVerError::PreRelease),
+            "pre",
+            "Increment Pre-Release Stage",
+        )?;
+        Ok(vec![change])
+    }
+
+    pub fn incr_build_num(&mut self) -> Result<Change> {
         let build = self
             .build
             .get_or_insert_with(buildmeta::BuildMetaData::default);
         Self::set_helper(
             build,
-            |b| {
-                b.increment_number()
-                    .map_err(|e| FuVerError::Error(e.to_string()))
-            },
+            |b| b.increment_number().map_err(FuVerError::BuildMeta),
+            "build",
             "Increment Build Number",
-            silent,
         )
     }
-    pub fn incr_build_date(&mut self, silent: bool) -> Result<()> {
+    pub fn incr_build_date(&mut self) -> Result<Change> {
         let build = self
             .build
             .get_or_insert_with(buildmeta::BuildMetaData::default);
         Self::set_helper(
             build,
-            |b| {
-                b.update_date()
-                    .map_err(|e| FuVerError::Error(e.to_string()))
-            },
+            |b| b.update_date().map_err(FuVerError::BuildMeta),
+            "build",
             "Increment Build Date",
-            silent,
         )
     }
-    pub fn incr_build_hash(&mut self, silent: bool) -> Result<()> {
+    pub fn incr_build_hash(&mut self) -> Result<Change> {
         let build = self
             .build
             .get_or_insert_with(buildmeta::BuildMetaData::default);
         Self::set_helper(
             build,
-            |b| {
-                b.update_hash()
-                    .map_err(|e| FuVerError::Error(e.to_string()))
-            },
+            |b| b.update_hash().map_err(FuVerError::BuildMeta),
+            "build",
             "Increment Build Hash",
-            silent,
         )
     }
 
-    pub fn incr_build_all(&mut self, silent: bool) -> Result<()> {
-        let current = {
-            let b = self.get_build()?;
-            b.to_string()
-        };
-        self.incr_build_num(true)?;
-        self.incr_build_date(true)?;
-        self.incr_build_hash(true)?;
-        if !silent {
-            let new = self.get_build()?;
-            println!("Increment Build {} -> {}", current, new);
-        }
-        Ok(())
+    pub fn incr_build_all(&mut self) -> Result<Change> {
+        let current = self.get_build()?.to_string();
+        self.incr_build_num()?;
+        self.incr_build_date()?;
+        self.incr_build_hash()?;
+        Ok(Change::new(
+            "build",
+            "Increment Build",
+            current,
+            self.get_build()?.to_string(),
+        ))
     }
 
-    pub fn show_version(&self) -> Result<()> {
-        println!("{}", &self.version);
-        Ok(())
-    }
+    /// Finalize the pre-release version. `1.4.0-rc.3` becomes `1.4.0`.
+    ///
+    /// If `update_build` is set, build metadata is refreshed with `incr_build_all`.
+    /// If `next` is given, the next development cycle is opened with a new
+    /// pre-release (`pre_tag` or the first stage).
+    /// Nothing is changed when any step fails.
+    ///
+    /// # Errors
+    /// pre-release is not defined
+    pub fn release(
+        &mut self,
+        next: Option<version::VersionPart>,
+        pre_tag: Option<&str>,
+        update_build: bool,
+    ) -> Result<Vec<Change>> {
+        let current = self.to_string();
+        let mut fv = self.clone();
+        fv.pre.take().ok_or(FuVerError::PreReleaseNotDefined)?;
+        if update_build {
+            fv.incr_build_all()?;
+        }
+        let released = fv.to_string();
 
-    pub fn show_major(&self) -> Result<()> {
-        self.version
-            .show_major()
-            .map_err(|e| FuVerError::Error(e.to_string()))
-    }
+        if let Some(part) = next {
+            fv.start_pre(part, pre_tag)?;
+        }
 
-    pub fn show_minor(&self) -> Result<()> {
-        self.version
-            .show_minor()
-            .map_err(|e| FuVerError::Error(e.to_string()))
+        *self = fv;
+        let mut changes = vec![Change::new("version", "Release", current, released.clone())];
+        if next.is_some() {
+            changes.push(Change::new(
+                "version",
+                "Next Version",
+                released,
+                self.to_string(),
+            ));
+        }
+        Ok(changes)
     }
 
-    pub fn show_patch(&self) -> Result<()> {
-        self.version
-            .show_patch()
-            .map_err(|e| FuVerError::Error(e.to_string()))
+    pub fn get_version(&self) -> &version::Version {
+        &self.version
     }
 
-    fn get_prerelease(&self) -> Result<&pre::PreRelease> {
+    pub fn get_pre(&self) -> Result<&pre::PreRelease> {
         self.pre.as_ref().ok_or(FuVerError::PreReleaseNotDefined)
     }
 
-    pub fn show_prerelease(&self) -> Result<()> {
-        self.get_prerelease()?
-            .show()
-            .map_err(|e| FuVerError::Error(e.to_string()))
-    }
-
-    pub fn show_prerelease_tag(&self) -> Result<()> {
-        self.get_prerelease()?
-            .show_tag()
-            .map_err(|e| FuVerError::Error(e.to_string()))
-    }
-
-    pub fn show_prerelease_number(&self) -> Result<()> {
-        self.get_prerelease()?
-            .show_number()
-            .map_err(|e| FuVerError::Error(e.to_string()))
-    }
-
-    fn get_build(&self) -> Result<&buildmeta::BuildMetaData> {
+    pub fn get_build(&self) -> Result<&buildmeta::BuildMetaData> {
         self.build
             .as_ref()
             .ok_or(FuVerError::BuildMetaDataNotDefined)
     }
 
-    pub fn show_build_fmt(&self, fmt: &str) -> Result<()> {
-        self.get_build()?
-            .show_fmt(fmt)
-            .map_err(|_| FuVerError::BuildMetaDataNotDefined)
+    /// `[git]` section, or the default
+    pub fn get_git(&self) -> git::GitConfig {
+        self.git.clone().unwrap_or_default()
     }
 
-    pub fn show_build(&self) -> Result<()> {
-        self.get_build()?
-            .show()
-            .map_err(|e| FuVerError::Error(e.to_string()))
+    /// Set the `[git]` section. The default is not written.
+    pub fn set_git(&mut self, config: git::GitConfig) {
+        self.git = (config != git::GitConfig::default()).then_some(config);
     }
 
-    pub fn show_build_number(&self) -> Result<()> {
-        self.get_build()?
-            .show_number()
-            .map_err(|e| FuVerError::Error(e.to_string()))
+    pub fn get_sync(&self) -> Result<&sync::SyncConfig> {
+        self.sync.as_ref().ok_or(FuVerError::SyncNotDefined)
     }
 
-    pub fn show_build_date(&self) -> Result<()> {
-        self.get_build()?
-            .show_date()
-            .map_err(|e| FuVerError::Error(e.to_string()))
+    /// `[changelog]` section, or the default
+    pub fn get_changelog(&self) -> changelog::ChangelogConfig {
+        self.changelog.clone().unwrap_or_default()
     }
 
-    pub fn show_build_hash(&self) -> Result<()> {
+    /// BuildMetaData with format-string. see [`buildmeta::BuildMetaData::fmt_string`]
+    pub fn get_build_fmt(&self, fmt: &str) -> Result<String> {
         self.get_build()?
-            .show_hash()
-            .map_err(|e| FuVerError::Error(e.to_string()))
+            .fmt_string(fmt)
+            .map_err(FuVerError::BuildMeta)
     }
 
-    pub fn show_build_all(&self) -> Result<()> {
-        self.get_build()?
-            .show_all()
-            .map_err(|e| FuVerError::Error(e.to_string()))
+    /// version and pre-release without build metadata
+    pub fn core_string(&self) -> String {
+        match self.pre.as_ref() {
+            Some(p) => format!("{}-{}", self.version, p),
+            None => self.version.to_string(),
+        }
     }
 
-    pub fn show_full(&self) -> Result<()> {
-        println!("{}", &self);
-        Ok(())
+    /// Full SemVer string with the build metadata formatted with the configured format
+    pub fn full_string(&self) -> Result<String> {
+        Ok(match self.build.as_ref() {
+            Some(b) => format!("{}+{}", self.core_string(), b.format_string()?),
+            None => self.core_string(),
+        })
+    }
+
+    /// Apply `action` to a copy and replace self only when it succeeds.
+    ///
+    /// The field of the change is `pre` when only the pre-release changed and
+    /// it is still defined. Finishing a pre-release changes the `version`.
+    fn update_helper<F>(&mut self, action: F, action_name: &'static str) -> Result<Change>
+    where
+        F: FnOnce(&mut FuVer) -> Result<()>,
+    {
+        let current = self.core_string();
+        let mut fv = self.clone();
+        action(&mut fv)?;
+        let field = if fv.version == self.version && fv.pre.is_some() && fv.pre != self.pre {
+            "pre"
+        } else {
+            "version"
+        };
+        *self = fv;
+        Ok(Change::new(field, action_name, current, self.core_string()))
     }
 
-    fn set_helper<T, F>(target: &mut T, action: F, head: &str, silent: bool) -> Result<()>
+    fn set_helper<T, F>(
+        target: &mut T,
+        action: F,
+        field: &'static str,
+        action_name: &'static str,
+    ) -> Result<Change>
     where
         T: Display,
         F: FnOnce(&mut T) -> Result<()>,
     {
         let current_value = target.to_string();
         action(target)?;
-        if !silent {
-            println!("{} {} -> {}", head, current_value, target);
-        };
-        Ok(())
+        Ok(Change::new(
+            field,
+            action_name,
+            current_value,
+            target.to_string(),
+        ))
     }
 
-    pub fn set_version(&mut self, s: &str, silent: bool) -> Result<()> {
-        Self::set_helper(
-            &mut self.version,
-            |v| v.set(s).map_err(|e| FuVerError::Error(e.to_string())),
-            "Set Vesion",
-            silent,
-        )
+    /// Set version from a SemVer string.
+    ///
+    /// Pre-release and build metadata are replaced only when they are given,
+    /// e.g. `2.0.0-beta.3` or `2.0.0+build.5`.
+    pub fn set_version(&mut self, s: &str) -> Result<Change> {
+        let parsed = FuVer::parse(s)?;
+        if parsed.pre.is_none() && parsed.build.is_none() {
+            return Self::set_helper(
+                &mut self.version,
+                |v| {
+                    *v = parsed.version;
+                    Ok(())
+                },
+                "version",
+                "Set Version",
+            );
+        }
+
+        // 途中で失敗しても変更しないように複製に適用する
+        let current = self.to_string();
+        let mut fv = self.clone();
+        fv.version = parsed.version;
+        if let Some(p) = parsed.pre {
+            fv.pre = Some(p);
+        }
+        if let Some(b) = parsed.build {
+            let build = fv
+                .build
+                .get_or_insert_with(buildmeta::BuildMetaData::default);
+            build
+                .set_number(b.get_number())
+                .map_err(FuVerError::BuildMeta)?;
+            build
+                .set_format(&b.get_format())
+                .map_err(FuVerError::BuildMeta)?;
+        }
+        let new = fv.full_string()?;
+        *self = fv;
+        Ok(Change::new("version", "Set Version", current, new))
     }
 
-    pub fn set_major(&mut self, n: usize, silent: bool) -> Result<()> {
+    pub fn set_major(&mut self, n: usize) -> Result<Change> {
         Self::set_helper(
             &mut self.version,
-            |v| v.set_major(n).map_err(|e| FuVerError::Error(e.to_string())),
+            |v| v.set_major(n).map_err(FuVerError::Version),
+            "version",
             "Set Major Version",
-            silent,
         )
     }
 
-    pub fn set_minor(&mut self, n: usize, silent: bool) -> Result<()> {
+    pub fn set_minor(&mut self, n: usize) -> Result<Change> {
         Self::set_helper(
             &mut self.version,
-            |v| v.set_minor(n).map_err(|e| FuVerError::Error(e.to_string())),
+            |v| v.set_minor(n).map_err(FuVerError::Version),
+            "version",
             "Set Minor Version",
-            silent,
         )
     }
 
-    pub fn set_patch(&mut self, n: usize, silent: bool) -> Result<()> {
+    pub fn set_patch(&mut self, n: usize) -> Result<Change> {
         Self::set_helper(
             &mut self.version,
-            |v| v.set_patch(n).map_err(|e| FuVerError::Error(e.to_string())),
+            |v| v.set_patch(n).map_err(FuVerError::Version),
+            "version",
             "Set Patch Version",
-            silent,
         )
     }
 
-    pub fn set_pre(&mut self, tag: &str, number: Option<usize>, silent: bool) -> Result<()> {
+    pub fn set_pre(&mut self, tag: &str, number: Option<usize>) -> Result<Change> {
         let pre = self.pre.get_or_insert_with(pre::PreRelease::default);
         Self::set_helper(
             pre,
-            |p| {
-                p.set(tag, number)
-                    .map_err(|e| FuVerError::Error(e.to_string()))
-            },
+            |p| p.set(tag, number).map_err(FuVerError::PreRelease),
+            "pre",
             "Set Pre-Release",
-            silent,
         )
     }
 
-    pub fn set_build_number(&mut self, n: usize, silent: bool) -> Result<()> {
+    pub fn set_build_number(&mut self, n: usize) -> Result<Change> {
         let build = self
             .build
             .get_or_insert_with(buildmeta::BuildMetaData::default);
         Self::set_helper(
             build,
-            |b| {
-                b.set_number(n)
-                    .map_err(|e| FuVerError::Error(e.to_string()))
-            },
+            |b| b.set_number(n).map_err(FuVerError::BuildMeta),
+            "build",
             "Set Build Number",
-            silent,
         )
     }
-    pub fn set_build_date(&mut self, date: &str, silent: bool) -> Result<()> {
+    pub fn set_build_date(&mut self, date: &str) -> Result<Change> {
         let build = self
             .build
             .get_or_insert_with(buildmeta::BuildMetaData::default);
         Self::set_helper(
             build,
-            |b| {
-                b.set_date(date)
-                    .map_err(|e| FuVerError::Error(e.to_string()))
-            },
+            |b| b.set_date(date).map_err(FuVerError::BuildMeta),
+            "build",
             "Set Build Date",
-            silent,
         )
     }
 
-    pub fn set_build_hash(&mut self, hash: &str, silent: bool) -> Result<()> {
+    pub fn set_build_hash(&mut self, hash: &str) -> Result<Change> {
         let build = self
             .build
             .get_or_insert_with(buildmeta::BuildMetaData::default);
         Self::set_helper(
             build,
-            |b| {
-                b.set_hash(hash)
-                    .map_err(|e| FuVerError::Error(e.to_string()))
-            },
+            |b| b.set_hash(hash).map_err(FuVerError::BuildMeta),
+            "build",
             "Set Build Hash",
-            silent,
         )
     }
 
-    pub fn set_build_fmt(&mut self, fmt: &str, silent: bool) -> Result<()> {
+    /// Remove pre-release.
+    ///
+    /// # Errors
+    /// pre-release is not defined
+    pub fn unset_pre(&mut self) -> Result<Change> {
+        let current = self.to_string();
+        self.pre.take().ok_or(FuVerError::PreReleaseNotDefined)?;
+        Ok(Change::new(
+            "pre",
+            "Unset Pre-Release",
+            current,
+            self.to_string(),
+        ))
+    }
+
+    /// Remove build metadata.
+    ///
+    /// # Errors
+    /// build metadata is not defined
+    pub fn unset_build(&mut self) -> Result<Change> {
+        let current = self.to_string();
+        self.build
+            .take()
+            .ok_or(FuVerError::BuildMetaDataNotDefined)?;
+        Ok(Change::new(
+            "build",
+            "Unset Build",
+            current,
+            self.to_string(),
+        ))
+    }
+
+    pub fn set_build_fmt(&mut self, fmt: &str) -> Result<Change> {
         let build = self
             .build
             .get_or_insert_with(buildmeta::BuildMetaData::default);
         let current = build.get_format();
-        build
-            .set_format(fmt)
-            .map_err(|e| FuVerError::Error(e.to_string()))?;
-        if !silent {
-            println!("Set Build Format {} -> {}", current, build.get_format());
+        build.set_format(fmt).map_err(FuVerError::BuildMeta)?;
+        Ok(Change::new(
+            "build.format",
+            "Set Build Format",
+            current,
+            build.get_format(),
+        ))
+    }
+}
+
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let permissions = match fs::metadata(path) {
+        Ok(meta) => Some(meta.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    if permissions.as_ref().is_some_and(|p| p.readonly()) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is read-only", path.display()),
+        ));
+    }
+
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        )
+    })?;
+    let tmp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        process::id()
+    ));
+
+    let result = (|| {
+        let mut f = File::create(&tmp)?;
+        f.write_all(contents)?;
+        f.sync_all()?;
+        if let Some(p) = permissions {
+            fs::set_permissions(&tmp, p)?;
         }
-        Ok(())
+        fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+type ParseResult<T> = result::Result<T, (usize, String)>;
+
+/// Iterate dot-separated identifiers with their byte offset.
+fn split_identifiers(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    s.split(".").scan(0, |offset, part| {
+        let pos = *offset;
+        *offset += part.len() + 1;
+        Some((pos, part))
+    })
+}
+
+fn check_identifiers(s: &str, check: fn(&str) -> identifier::Result<()>) -> ParseResult<()> {
+    for (pos, part) in split_identifiers(s) {
+        check(part).map_err(|e| (pos, e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn parse_core(s: &str) -> ParseResult<version::Version> {
+    version::Version::from_str(s).map_err(|e| {
+        let pos = match &e {
+            version::VersionError::InvalidPart { part, .. } => {
+                let index = match *part {
+                    "major" => 0,
+                    "minor" => 1,
+                    _ => 2,
+                };
+                split_identifiers(s).nth(index).map_or(0, |(pos, _)| pos)
+            }
+            version::VersionError::Format(_) => 0,
+        };
+        (pos, e.to_string())
+    })
+}
+
+fn parse_pre(s: &str) -> ParseResult<pre::PreRelease> {
+    check_identifiers(s, identifier::check_prerelease_identifier)?;
+    pre::PreRelease::from_str(s).map_err(|e| (0, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fuver(version: &str, pre: Option<(&str, Option<usize>)>) -> FuVer {
+        FuVer {
+            stages: None,
+            git: None,
+            sync: None,
+            changelog: None,
+            version: version::Version::from_str(version).unwrap(),
+            pre: pre.map(|(tag, number)| match number {
+                Some(n) => pre::PreRelease::with_number(tag, n).unwrap(),
+                None => pre::PreRelease::new(tag).unwrap(),
+            }),
+            build: None,
+        }
+    }
+
+    #[test]
+    fn test_ordering_semver_spec() {
+        // SemVer 2.0 仕様書 11章の例
+        let versions = [
+            fuver("1.0.0", Some(("alpha", None))),
+            fuver("1.0.0", Some(("alpha", Some(1)))),
+            fuver("1.0.0", Some(("beta", None))),
+            fuver("1.0.0", Some(("beta", Some(2)))),
+            fuver("1.0.0", Some(("beta", Some(11)))),
+            fuver("1.0.0", Some(("rc", Some(1)))),
+            fuver("1.0.0", None),
+            fuver("2.0.0", None),
+            fuver("2.1.0", None),
+            fuver("2.1.1", None),
+        ];
+        for w in versions.windows(2) {
+            assert!(w[0] < w[1], "{} < {}", w[0], w[1]);
+        }
+    }
+
+    #[test]
+    fn test_ordering_ignores_build() {
+        let mut a = fuver("1.0.0", None);
+        let b = fuver("1.0.0", None);
+        a.build = Some(buildmeta::BuildMetaData::new(
+            5,
+            String::new(),
+            String::new(),
+            "{number}".to_string(),
+        ));
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut versions = [
+            fuver("1.0.0", None),
+            fuver("0.9.0", None),
+            fuver("1.0.0", Some(("rc", Some(1)))),
+        ];
+        versions.sort();
+        let sorted: Vec<String> = versions.iter().map(|v| v.to_string()).collect();
+        assert_eq!(sorted, vec!["0.9.0", "1.0.0-rc.1", "1.0.0"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let fv = FuVer::parse("1.2.3").unwrap();
+        assert_eq!(fv.version.to_string(), "1.2.3");
+        assert!(fv.pre.is_none());
+        assert!(fv.build.is_none());
+
+        let fv = FuVer::parse("2.0.0-beta.3").unwrap();
+        assert_eq!(fv.version.to_string(), "2.0.0");
+        assert_eq!(fv.pre.unwrap().to_string(), "beta.3");
+
+        let fv = FuVer::parse("1.2.0-feat-login.3").unwrap();
+        assert_eq!(fv.pre.unwrap().to_string(), "feat-login.3");
+
+        let fv = FuVer::parse("1.0.0-alpha.1.feature-x").unwrap();
+        assert_eq!(fv.pre.unwrap().to_string(), "alpha.1.feature-x");
+
+        let fv = FuVer::parse("1.2.3-rc.1+build.5").unwrap();
+        assert_eq!(fv.pre.unwrap().to_string(), "rc.1");
+        let build = fv.build.unwrap();
+        assert_eq!(build.get_number(), 5);
+        assert_eq!(build.get_format(), "build.{number}");
+    }
+
+    fn parse_err(s: &str) -> ParseError {
+        match FuVer::parse(s) {
+            Err(FuVerError::Parse(e)) => e,
+            _ => panic!("Expected FuVerError::Parse: {}", s),
+        }
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        assert_eq!(parse_err("1.2").position, 0);
+        assert_eq!(parse_err("1.a.3").position, 2);
+        assert_eq!(parse_err("1.02.3").position, 2);
+        assert_eq!(parse_err("1.2.3-").position, 6);
+        assert_eq!(parse_err("1.2.3-rc.01").position, 9);
+        assert_eq!(parse_err("1.2.3-rc.1+").position, 11);
+        assert_eq!(parse_err("1.2.3+build.a_b").position, 12);
+    }
+
+    #[test]
+    fn test_set_version_full() {
+        let mut fv = fuver("0.1.0", None);
+        fv.set_version("2.0.0-beta.3").unwrap();
+        assert_eq!(fv.to_string(), "2.0.0-beta.3");
+
+        // pre-release が指定されていなければ既存の値を残す
+        fv.set_version("2.0.1").unwrap();
+        assert_eq!(fv.to_string(), "2.0.1-beta.3");
+
+        assert!(fv.set_version("2.0.0-").is_err());
+        assert_eq!(fv.to_string(), "2.0.1-beta.3");
+
+        let change = fv.set_version("3.0.0-rc.1+build.7").unwrap();
+        assert_eq!(change.action, "Set Version");
+        assert_eq!(change.new, "3.0.0-rc.1+build.7");
+        assert_eq!(fv.to_string(), "3.0.0-rc.1+build.7");
+        assert_eq!(fv.full_string().unwrap(), "3.0.0-rc.1+build.7");
+    }
+
+    #[test]
+    fn test_unset() {
+        let mut fv = FuVer::parse("1.2.3-rc.1+build.5").unwrap();
+        fv.unset_pre().unwrap();
+        assert!(fv.pre.is_none());
+        assert!(matches!(
+            fv.unset_pre(),
+            Err(FuVerError::PreReleaseNotDefined)
+        ));
+
+        fv.unset_build().unwrap();
+        assert!(fv.build.is_none());
+        assert!(matches!(
+            fv.unset_build(),
+            Err(FuVerError::BuildMetaDataNotDefined)
+        ));
+        assert_eq!(fv.to_string(), "1.2.3");
+    }
+
+    #[test]
+    fn test_release() {
+        let mut fv = FuVer::parse("1.4.0-rc.3").unwrap();
+        fv.release(None, None, false).unwrap();
+        assert_eq!(fv.to_string(), "1.4.0");
+
+        // プレリリースでなければリリースできない
+        assert!(matches!(
+            fv.release(None, None, false),
+            Err(FuVerError::PreReleaseNotDefined)
+        ));
+    }
+
+    #[test]
+    fn test_release_next() {
+        let mut fv = FuVer::parse("1.4.0-rc.3").unwrap();
+        fv.release(Some(version::VersionPart::Minor), None, false)
+            .unwrap();
+        assert_eq!(fv.to_string(), "1.5.0-alpha.1");
+
+        let mut fv = FuVer::parse("1.4.0-rc.3").unwrap();
+        fv.release(Some(version::VersionPart::Patch), Some("dev"), false)
+            .unwrap();
+        assert_eq!(fv.to_string(), "1.4.1-dev.1");
+    }
+
+    #[test]
+    fn test_release_atomic() {
+        let mut fv = FuVer::parse("1.4.0-rc.3").unwrap();
+        let result = fv.release(Some(version::VersionPart::Major), Some(""), false);
+        assert!(result.is_err());
+        assert_eq!(fv.to_string(), "1.4.0-rc.3");
+    }
+
+    #[test]
+    fn test_incr_pre_stage() {
+        let mut fv = FuVer::parse("1.0.0-alpha.4").unwrap();
+        fv.incr_pre_stage(false).unwrap();
+        assert_eq!(fv.to_string(), "1.0.0-beta.1");
+        fv.incr_pre_stage(false).unwrap();
+        assert_eq!(fv.to_string(), "1.0.0-rc.1");
+
+        // 最後のステージは --release がなければエラー
+        assert!(fv.incr_pre_stage(false).is_err());
+        assert_eq!(fv.to_string(), "1.0.0-rc.1");
+
+        fv.incr_pre_stage(true).unwrap();
+        assert_eq!(fv.to_string(), "1.0.0");
+    }
+
+    #[test]
+    fn test_incr_pre_stage_config() {
+        let mut fv = FuVer::from_str(
+            r#"
+stages = ["dev", "preview"]
+
+[version]
+major = 1
+minor = 0
+patch = 0
+
+[pre]
+tag = "dev"
+number = 2
+"#,
+        )
+        .unwrap();
+        fv.incr_pre_stage(false).unwrap();
+        assert_eq!(fv.to_string(), "1.0.0-preview.1");
+    }
+
+    #[test]
+    fn test_incr_finishes_pending_release() {
+        let mut fv = FuVer::parse("1.0.0-alpha.2").unwrap();
+        fv.incr_ver_patch().unwrap();
+        assert_eq!(fv.to_string(), "1.0.0");
+        fv.incr_ver_patch().unwrap();
+        assert_eq!(fv.to_string(), "1.0.1");
+
+        let mut fv = FuVer::parse("1.2.0-rc.1").unwrap();
+        fv.incr_ver_minor().unwrap();
+        assert_eq!(fv.to_string(), "1.2.0");
+
+        let mut fv = FuVer::parse("1.2.1-rc.1").unwrap();
+        fv.incr_ver_minor().unwrap();
+        assert_eq!(fv.to_string(), "1.3.0");
+
+        let mut fv = FuVer::parse("2.0.0-beta.1").unwrap();
+        fv.incr_ver_major().unwrap();
+        assert_eq!(fv.to_string(), "2.0.0");
+
+        let mut fv = FuVer::parse("2.1.0-beta.1").unwrap();
+        fv.incr_ver_major().unwrap();
+        assert_eq!(fv.to_string(), "3.0.0");
+    }
+
+    #[test]
+    fn test_incr_mask_pre_release() {
+        let fv = FuVer::parse("1.0.0-alpha.2").unwrap();
+        for (mask, expected) in [
+            ("1.x.x", "1.0.0"),
+            ("2.x.x", "2.0.0"),
+            ("x.1.x", "1.0.0"),
+            ("x.x.1", "1.0.0"),
+            ("x.x.2", "1.0.1"),
+        ] {
+            let mut v = fv.clone();
+            let change = v.incr_ver_mask(mask).unwrap();
+            assert_eq!(v.to_string(), expected, "{}", mask);
+            assert_eq!(change.old, "1.0.0-alpha.2");
+            assert_eq!(change.new, expected);
+        }
+
+        let mut fv = FuVer::parse("1.2.1-rc.1").unwrap();
+        fv.incr_ver_mask("x.1.x").unwrap();
+        assert_eq!(fv.to_string(), "1.3.0");
+    }
+
+    #[test]
+    fn test_incr_pre_versions() {
+        let mut fv = FuVer::parse("1.2.3").unwrap();
+        fv.incr_premajor(None).unwrap();
+        assert_eq!(fv.to_string(), "2.0.0-alpha.1");
+
+        let mut fv = FuVer::parse("1.2.3").unwrap();
+        fv.incr_preminor(Some("beta")).unwrap();
+        assert_eq!(fv.to_string(), "1.3.0-beta.1");
+
+        let mut fv = FuVer::parse("1.2.3-rc.2").unwrap();
+        fv.incr_prepatch(None).unwrap();
+        assert_eq!(fv.to_string(), "1.2.4-alpha.1");
+    }
+
+    #[test]
+    fn test_incr_prerelease() {
+        let mut fv = FuVer::parse("1.2.3").unwrap();
+        fv.incr_prerelease(None).unwrap();
+        assert_eq!(fv.to_string(), "1.2.4-alpha.1");
+        fv.incr_prerelease(None).unwrap();
+        assert_eq!(fv.to_string(), "1.2.4-alpha.2");
+        fv.incr_prerelease(Some("alpha")).unwrap();
+        assert_eq!(fv.to_string(), "1.2.4-alpha.3");
+        fv.incr_prerelease(Some("beta")).unwrap();
+        assert_eq!(fv.to_string(), "1.2.4-beta.1");
+
+        assert!(fv.incr_prerelease(Some("in valid")).is_err());
+        assert_eq!(fv.to_string(), "1.2.4-beta.1");
+    }
+
+    #[test]
+    fn test_change_record() {
+        let mut fv = FuVer::parse("1.0.0-alpha.2").unwrap();
+        let change = fv.incr_ver_patch().unwrap();
+        assert_eq!(change.field, "version");
+        assert_eq!(change.old, "1.0.0-alpha.2");
+        assert_eq!(change.new, "1.0.0");
+        assert_eq!(
+            change.to_string(),
+            "Increment Patch Version 1.0.0-alpha.2 -> 1.0.0"
+        );
+
+        let change = fv.set_pre("beta", Some(1)).unwrap();
+        assert_eq!(change.field, "pre");
+        assert_eq!(change.new, "beta.1");
+
+        let change = fv.incr_pre().unwrap();
+        assert_eq!(change.field, "pre");
+        assert_eq!(change.new, "1.0.0-beta.2");
+        let change = fv.incr_ver_major().unwrap();
+        assert_eq!(change.field, "version");
+    }
+
+    #[test]
+    fn test_error_source() {
+        let mut fv = FuVer::parse("1.0.0-rc.1").unwrap();
+        let err = fv.set_pre("", None).unwrap_err();
+        assert!(matches!(
+            err,
+            FuVerError::PreRelease(pre::PreReleaseError::Format(_))
+        ));
+
+        let source = error::Error::source(&err).unwrap();
+        assert_eq!(
+            source.to_string(),
+            pre::PreReleaseError::Format(identifier::FormatError::EmptyTag).to_string()
+        );
+        assert!(source.source().is_some());
+    }
+
+    #[test]
+    fn test_build_fmt_error() {
+        let fv = FuVer::parse("1.0.0+build.5").unwrap();
+        assert_eq!(fv.get_build_fmt("b.{number}").unwrap(), "b.5");
+
+        // 書式のエラーは未定義エラーにならない
+        assert!(matches!(
+            fv.get_build_fmt("b..{number}"),
+            Err(FuVerError::BuildMeta(buildmeta::BuildMetaError::Format(_)))
+        ));
+
+        let fv = FuVer::parse("1.0.0").unwrap();
+        assert!(matches!(
+            fv.get_build_fmt("{number}"),
+            Err(FuVerError::BuildMetaDataNotDefined)
+        ));
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("fuver-{}-{}.toml", name, process::id()))
+    }
+
+    #[test]
+    fn test_save() {
+        let path = temp_path("save");
+        let fv = FuVer::parse("1.2.3-rc.1").unwrap();
+        fv.save(path.to_str().unwrap()).unwrap();
+
+        let saved = FuVer::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.to_string(), "1.2.3-rc.1");
+
+        // 一時ファイルが残っていない
+        let dir = path.parent().unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let leftover = fs::read_dir(dir).unwrap().filter_map(|e| e.ok()).any(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .starts_with(&format!(".{}", name))
+        });
+        assert!(!leftover);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_save_readonly() {
+        let path = temp_path("readonly");
+        fs::write(&path, "").unwrap();
+        let mut perm = fs::metadata(&path).unwrap().permissions();
+        perm.set_readonly(true);
+        fs::set_permissions(&path, perm.clone()).unwrap();
+
+        let fv = FuVer::default();
+        assert!(matches!(
+            fv.save(path.to_str().unwrap()),
+            Err(FuVerError::IO(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        #[allow(clippy::permissions_set_readonly_false)]
+        perm.set_readonly(false);
+        fs::set_permissions(&path, perm).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("permissions");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        FuVer::default().save(path.to_str().unwrap()).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_save_missing_dir() {
+        let path = temp_path("missing").join("fuver.toml");
+        assert!(matches!(
+            FuVer::default().save(path.to_str().unwrap()),
+            Err(FuVerError::IO(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_invalid_document() {
+        let path = temp_path("invalid");
+        fs::write(&path, "[version\nmajor = 1\n").unwrap();
+        assert!(matches!(
+            FuVer::default().save(path.to_str().unwrap()),
+            Err(FuVerError::Document(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[version\nmajor = 1\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_save_keeps_comments() {
+        let path = temp_path("comments");
+        let original = r#"# リリースごとに更新する
+[version]
+major = 1 # メジャー
+minor = 2
+patch = 3
+str = "1.2.3"
+
+[pre]
+tag = "rc" # 次はリリース
+number = 1
+"#;
+        fs::write(&path, original).unwrap();
+
+        let mut fv = FuVer::from_str(original).unwrap();
+        fv.incr_pre().unwrap();
+        fv.save(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            original.replace("number = 1", "number = 2")
+        );
+
+        fv.incr_ver_patch().unwrap();
+        fv.save(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            r#"# リリースごとに更新する
+[version]
+major = 1 # メジャー
+minor = 2
+patch = 3
+str = "1.2.3"
+"#
+        );
+        fs::remove_file(&path).unwrap();
     }
 }
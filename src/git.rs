@@ -0,0 +1,408 @@
+use std::{error, fmt, path::Path, result};
+
+use git2::{Oid, Repository, Sort, StatusOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::buildmeta;
+use crate::fuver::FuVer;
+
+const VERSION_PLACEHOLDER: &str = "{version}";
+
+#[derive(Debug)]
+pub enum TagError {
+    Git(git2::Error),
+    BuildMeta(buildmeta::BuildMetaError),
+    Dirty(Vec<String>),
+    Exists(String),
+}
+
+impl fmt::Display for TagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagError::Git(e) => write!(f, "{}", e),
+            TagError::BuildMeta(e) => write!(f, "{}", e),
+            TagError::Dirty(files) => write!(
+                f,
+                "コミットされていない変更があります: {} (--forceで無視)",
+                files.join(", ")
+            ),
+            TagError::Exists(name) => {
+                write!(f, "タグ {} はすでに存在します (--forceで上書き)", name)
+            }
+        }
+    }
+}
+
+impl error::Error for TagError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            TagError::Git(e) => Some(e),
+            TagError::BuildMeta(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<git2::Error> for TagError {
+    fn from(e: git2::Error) -> Self {
+        TagError::Git(e)
+    }
+}
+
+impl From<buildmeta::BuildMetaError> for TagError {
+    fn from(e: buildmeta::BuildMetaError) -> Self {
+        TagError::BuildMeta(e)
+    }
+}
+
+/// `[git]` section
+///
+/// ```toml
+/// [git]
+/// tag = "v{version}"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GitConfig {
+    /// Release tag name. `{version}` is replaced with the version.
+    #[serde(default = "default_tag")]
+    pub tag: String,
+}
+
+fn default_tag() -> String {
+    format!("v{}", VERSION_PLACEHOLDER)
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self { tag: default_tag() }
+    }
+}
+
+impl GitConfig {
+    /// Tags named `{prefix}{version}`
+    pub fn with_prefix(prefix: &str) -> GitConfig {
+        GitConfig {
+            tag: format!("{}{}", prefix, VERSION_PLACEHOLDER),
+        }
+    }
+
+    /// Tag name of `version`
+    pub fn tag_name(&self, version: &str) -> String {
+        self.tag.replace(VERSION_PLACEHOLDER, version)
+    }
+
+    /// Version of a tag name following the template
+    pub fn parse_tag(&self, name: &str) -> Option<FuVer> {
+        let (prefix, suffix) = self
+            .tag
+            .split_once(VERSION_PLACEHOLDER)
+            .unwrap_or((&self.tag, ""));
+        let version = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        FuVer::parse(version).ok()
+    }
+}
+
+/// Open the repository containing `dir`.
+pub fn open(dir: &Path) -> Result<Repository, git2::Error> {
+    Repository::discover(dir)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    pub id: String,
+    /// first line of the message
+    pub summary: String,
+    pub message: String,
+    /// commit time in seconds since the epoch
+    pub time: i64,
+}
+
+/// Release tag found by [`last_release`]
+#[derive(Debug, Clone)]
+pub struct ReleaseTag {
+    pub name: String,
+    pub version: FuVer,
+    pub commit: Oid,
+}
+
+/// Commit of the tag `name`. `None` when the tag does not point to a commit
+/// (e.g. a tag of a blob).
+pub(crate) fn tag_commit(repo: &Repository, name: &str) -> Option<Oid> {
+    let object = repo.revparse_single(&format!("refs/tags/{}", name)).ok()?;
+    Some(object.peel_to_commit().ok()?.id())
+}
+
+/// Release tags following `config`, reachable from HEAD.
+pub fn release_tags(repo: &Repository, config: &GitConfig) -> Result<Vec<ReleaseTag>, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?.id();
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let Some(version) = config.parse_tag(name) else {
+            continue;
+        };
+        let Some(commit) = tag_commit(repo, name) else {
+            continue;
+        };
+        if commit == head || repo.graph_descendant_of(head, commit)? {
+            tags.push(ReleaseTag {
+                name: name.to_string(),
+                version,
+                commit,
+            });
+        }
+    }
+    tags.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(tags)
+}
+
+/// The highest release tag reachable from HEAD.
+pub fn last_release(
+    repo: &Repository,
+    config: &GitConfig,
+) -> Result<Option<ReleaseTag>, git2::Error> {
+    Ok(release_tags(repo, config)?.pop())
+}
+
+/// The highest release tag reachable from HEAD that is lower than `version`.
+///
+/// Unlike [`last_release`], a tag of `version` itself is skipped, so the
+/// result is the same before and after tagging the release.
+pub fn previous_release(
+    repo: &Repository,
+    config: &GitConfig,
+    version: &FuVer,
+) -> Result<Option<ReleaseTag>, git2::Error> {
+    let mut tags = release_tags(repo, config)?;
+    tags.retain(|t| t.version < *version);
+    Ok(tags.pop())
+}
+
+/// Commits reachable from HEAD but not from `since`, newest first.
+pub fn commits_since(
+    repo: &Repository,
+    since: Option<Oid>,
+) -> Result<Vec<CommitInfo>, git2::Error> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push_head()?;
+    if let Some(oid) = since {
+        walk.hide(oid)?;
+    }
+    walk.map(|oid| {
+        let commit = repo.find_commit(oid?)?;
+        Ok(CommitInfo {
+            id: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            message: commit.message().unwrap_or_default().to_string(),
+            time: commit.time().seconds(),
+        })
+    })
+    .collect()
+}
+
+/// Paths with uncommitted changes. Untracked and ignored files are not counted.
+pub fn dirty_files(repo: &Repository) -> Result<Vec<String>, git2::Error> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(false).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut opts))?;
+    Ok(statuses
+        .iter()
+        .filter(|s| !s.status().is_empty())
+        .filter_map(|s| s.path().map(str::to_string))
+        .collect())
+}
+
+/// Annotated release tag to create at HEAD
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagPlan {
+    pub name: String,
+    pub message: String,
+    pub commit: Oid,
+}
+
+/// Plan an annotated tag of `version` at HEAD.
+///
+/// # Errors
+/// Without `force`:
+/// * the worktree has uncommitted changes ([`TagError::Dirty`])
+/// * the tag already exists ([`TagError::Exists`])
+pub fn plan_tag(
+    repo: &Repository,
+    config: &GitConfig,
+    version: &FuVer,
+    force: bool,
+) -> result::Result<TagPlan, TagError> {
+    let name = config.tag_name(&version.core_string());
+    let commit = repo.head()?.peel_to_commit()?.id();
+    if !force {
+        let dirty = dirty_files(repo)?;
+        if !dirty.is_empty() {
+            return Err(TagError::Dirty(dirty));
+        }
+        if repo.find_reference(&format!("refs/tags/{}", name)).is_ok() {
+            return Err(TagError::Exists(name));
+        }
+    }
+    Ok(TagPlan {
+        message: tag_message(version)?,
+        name,
+        commit,
+    })
+}
+
+/// Annotation of a release tag: the full version and each build metadata field.
+fn tag_message(version: &FuVer) -> buildmeta::Result<String> {
+    let Ok(build) = version.get_build() else {
+        return Ok(format!("Release {}\n", version.core_string()));
+    };
+    Ok(format!(
+        "Release {}+{}\n\nbuild number: {}\nbuild date: {}\nbuild hash: {}\n",
+        version.core_string(),
+        build.format_string()?,
+        build.get_number(),
+        build.get_date(),
+        build.get_hash()
+    ))
+}
+
+/// Create the tag of `plan`, replacing an existing one.
+pub fn create_tag(repo: &Repository, plan: &TagPlan) -> Result<Oid, git2::Error> {
+    let target = repo.find_object(plan.commit, None)?;
+    let signature = repo.signature()?;
+    repo.tag(&plan.name, &target, &signature, &plan.message, true)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::{env, fs, path::PathBuf, process};
+
+    /// Empty repository in a temporary directory
+    pub(crate) fn temp_repo(name: &str) -> (PathBuf, Repository) {
+        let dir = env::temp_dir().join(format!("fuver-git-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let repo = Repository::init(&dir).unwrap();
+        (dir, repo)
+    }
+
+    /// Commit all files in the worktree
+    pub(crate) fn commit(repo: &Repository, message: &str) -> Oid {
+        let sig = Signature::now("fuver", "fuver@example.com").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    pub(crate) fn tag(repo: &Repository, name: &str) {
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.tag_lightweight(name, head.as_object(), false).unwrap();
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        let config = GitConfig::default();
+        assert_eq!(config.tag_name("1.2.3"), "v1.2.3");
+        assert_eq!(
+            config.parse_tag("v1.2.3-rc.1"),
+            Some(FuVer::parse("1.2.3-rc.1").unwrap())
+        );
+        assert_eq!(config.parse_tag("1.2.3"), None);
+        assert_eq!(config.parse_tag("vnext"), None);
+
+        assert_eq!(GitConfig::with_prefix("v"), config);
+        let config = GitConfig {
+            tag: "app-{version}-release".to_string(),
+        };
+        assert!(config.parse_tag("app-1.0.0-release").is_some());
+        assert!(config.parse_tag("app-1.0.0").is_none());
+    }
+
+    #[test]
+    fn test_plan_tag() {
+        let (dir, repo) = temp_repo("tag");
+        repo.config()
+            .unwrap()
+            .set_str("user.name", "fuver")
+            .unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.email", "fuver@example.com")
+            .unwrap();
+        fs::write(dir.join("a"), "1").unwrap();
+        let head = commit(&repo, "feat: first");
+        let config = GitConfig::default();
+        let version = FuVer::parse("1.0.0-rc.1+build.5").unwrap();
+
+        let plan = plan_tag(&repo, &config, &version, false).unwrap();
+        assert_eq!(plan.name, "v1.0.0-rc.1");
+        assert_eq!(plan.commit, head);
+        assert!(plan
+            .message
+            .starts_with("Release 1.0.0-rc.1+build.5\n\nbuild number: 5\n"));
+        create_tag(&repo, &plan).unwrap();
+        let tag = repo
+            .revparse_single("refs/tags/v1.0.0-rc.1")
+            .unwrap()
+            .into_tag()
+            .unwrap();
+        assert_eq!(tag.message(), Some(plan.message.as_str()));
+
+        assert!(matches!(
+            plan_tag(&repo, &config, &version, false),
+            Err(TagError::Exists(_))
+        ));
+        fs::write(dir.join("a"), "2").unwrap();
+        let version = FuVer::parse("1.0.0").unwrap();
+        assert!(matches!(
+            plan_tag(&repo, &config, &version, false),
+            Err(TagError::Dirty(files)) if files == ["a"]
+        ));
+        assert!(plan_tag(&repo, &config, &version, true).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_last_release_and_commits() {
+        let (dir, repo) = temp_repo("release");
+        fs::write(dir.join("a"), "1").unwrap();
+        commit(&repo, "feat: first");
+        tag(&repo, "v0.2.0");
+        tag(&repo, "v0.10.0");
+        tag(&repo, "latest");
+        fs::write(dir.join("a"), "2").unwrap();
+        let second = commit(&repo, "fix: second");
+
+        // HEAD から辿れないタグは無視する
+        let sig = Signature::now("fuver", "fuver@example.com").unwrap();
+        let tree = repo.find_commit(second).unwrap().tree().unwrap();
+        let orphan = repo.commit(None, &sig, &sig, "orphan", &tree, &[]).unwrap();
+        repo.tag_lightweight("v9.0.0", &repo.find_object(orphan, None).unwrap(), false)
+            .unwrap();
+        // コミットを指さないタグも無視する
+        let blob = repo.blob(b"not a commit").unwrap();
+        repo.tag_lightweight("v8.0.0", &repo.find_object(blob, None).unwrap(), false)
+            .unwrap();
+
+        let config = GitConfig::default();
+        let last = last_release(&repo, &config).unwrap().unwrap();
+        assert_eq!(last.name, "v0.10.0");
+        let version = FuVer::parse("0.10.0").unwrap();
+        let prev = previous_release(&repo, &config, &version).unwrap().unwrap();
+        assert_eq!(prev.name, "v0.2.0");
+
+        let commits = commits_since(&repo, Some(last.commit)).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].summary, "fix: second");
+        assert_eq!(commits_since(&repo, None).unwrap().len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
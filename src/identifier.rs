@@ -1,4 +1,4 @@
-use std::{fmt, result};
+use std::{cmp::Ordering, error, fmt, result};
 
 pub type Result<T> = result::Result<T, FormatError>;
 
@@ -6,6 +6,7 @@ pub type Result<T> = result::Result<T, FormatError>;
 pub enum FormatError {
     EmptyTag,
     InvalidChar(char),
+    LeadingZero(String),
 }
 
 impl fmt::Display for FormatError {
@@ -15,10 +16,15 @@ impl fmt::Display for FormatError {
             FormatError::InvalidChar(c) => {
                 write!(f, "対応していない文字が含まれています: {}", c)
             }
+            FormatError::LeadingZero(s) => {
+                write!(f, "数値の識別子は0から始められません: {}", s)
+            }
         }
     }
 }
 
+impl error::Error for FormatError {}
+
 pub fn check_dot_separated_identifiers(s: &str) -> Result<()> {
     if s.is_empty() {
         return Err(FormatError::EmptyTag);
@@ -42,3 +48,59 @@ pub fn check_identifier(s: &str) -> Result<()> {
         Some(b) => Err(FormatError::InvalidChar(b as char)),
     }
 }
+
+/// Check a pre-release identifier.
+///
+/// In addition to [`check_identifier`], numeric identifiers must not have leading zeros.
+pub fn check_prerelease_identifier(s: &str) -> Result<()> {
+    check_identifier(s)?;
+    if s.len() > 1 && s.starts_with('0') && is_numeric(s) {
+        return Err(FormatError::LeadingZero(s.to_string()));
+    }
+    Ok(())
+}
+
+/// Compare two identifiers according to SemVer 2.0 precedence.
+///
+/// Numeric identifiers are compared numerically and always have lower
+/// precedence than alphanumeric identifiers, which are compared in ASCII order.
+pub fn compare_identifier(a: &str, b: &str) -> Ordering {
+    match (is_numeric(a), is_numeric(b)) {
+        (true, true) => {
+            // 桁あふれしないように数値へ変換せず桁数と文字列で比較する
+            let x = a.trim_start_matches('0');
+            let y = b.trim_start_matches('0');
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        }
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.cmp(b),
+    }
+}
+
+/// Compare dot-separated identifier lists field by field.
+///
+/// If all shared fields are equal, the longer list has higher precedence.
+pub fn compare_identifiers<'a, I, J>(a: I, b: J) -> Ordering
+where
+    I: IntoIterator<Item = &'a str>,
+    J: IntoIterator<Item = &'a str>,
+{
+    let mut a = a.into_iter();
+    let mut b = b.into_iter();
+    loop {
+        match (a.next(), b.next()) {
+            (Some(x), Some(y)) => match compare_identifier(x, y) {
+                Ordering::Equal => continue,
+                o => return o,
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+pub fn is_numeric(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
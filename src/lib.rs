@@ -1,7 +1,19 @@
 pub mod buildmeta;
+pub mod changelog;
 pub mod cli;
+pub mod config;
+pub mod conventional;
+mod diff;
+mod document;
+pub mod git;
 mod identifier;
+pub use identifier::FormatError;
+pub mod lock;
+pub mod output;
 pub mod pre;
+pub mod storage;
+pub mod sync;
+pub mod verify;
 pub mod version;
 
 pub mod fuver;
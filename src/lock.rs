@@ -0,0 +1,148 @@
+use std::{
+    error, fmt,
+    fs::{self, File, OpenOptions, TryLockError},
+    io,
+    path::{Path, PathBuf},
+    result, thread,
+    time::{Duration, Instant},
+};
+
+pub type Result<T> = result::Result<T, LockError>;
+
+#[derive(Debug)]
+pub enum LockError {
+    IO(io::Error),
+    Timeout(PathBuf, Duration),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::IO(e) => write!(f, "ロックファイルを開けません: {}", e),
+            LockError::Timeout(p, t) => write!(
+                f,
+                "{}秒待ってもロックを取得できませんでした: {}",
+                t.as_secs_f64(),
+                p.display()
+            ),
+        }
+    }
+}
+
+impl error::Error for LockError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LockError::IO(e) => Some(e),
+            LockError::Timeout(..) => None,
+        }
+    }
+}
+
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Advisory lock of a config file.
+///
+/// The lock is taken on a sidecar file (`.fuver.toml.lock`) instead of the config itself,
+/// because `FuVer::save` replaces the config file by renaming.
+/// The lock is released and the sidecar file is removed on drop.
+#[derive(Debug)]
+pub struct ConfigLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl ConfigLock {
+    /// Lock `config`, waiting up to `timeout`.
+    ///
+    /// # Errors
+    /// * the lock file cannot be opened
+    /// * another process holds the lock longer than `timeout`
+    pub fn acquire(config: &Path, timeout: Duration) -> Result<ConfigLock> {
+        let path = lock_path(config);
+        let start = Instant::now();
+        loop {
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .map_err(LockError::IO)?;
+            loop {
+                match file.try_lock() {
+                    Ok(()) => break,
+                    Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                        thread::sleep(RETRY_INTERVAL)
+                    }
+                    Err(TryLockError::WouldBlock) => return Err(LockError::Timeout(path, timeout)),
+                    Err(TryLockError::Error(e)) => return Err(LockError::IO(e)),
+                }
+            }
+            // 待っている間に前の所有者がファイルを削除していたら開き直す
+            if is_same_file(&file, &path) {
+                return Ok(ConfigLock { path, _file: file });
+            }
+        }
+    }
+}
+
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        // ロックを持ったまま削除する。ファイルはこの後に閉じられる
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// `path` still refers to the opened `file`.
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+fn lock_path(config: &Path) -> PathBuf {
+    let name = config
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    config.with_file_name(format!(".{}.lock", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn test_lock_path() {
+        assert_eq!(
+            lock_path(Path::new("a/fuver.toml")),
+            PathBuf::from("a/.fuver.toml.lock")
+        );
+    }
+
+    #[test]
+    fn test_lock_timeout() {
+        let config = std::env::temp_dir().join(format!("fuver-lock-{}.toml", process::id()));
+        let lock = ConfigLock::acquire(&config, Duration::ZERO).unwrap();
+
+        match ConfigLock::acquire(&config, Duration::from_millis(50)) {
+            Err(LockError::Timeout(p, _)) => assert_eq!(p, lock_path(&config)),
+            _ => panic!("Expected Timeout error"),
+        }
+
+        drop(lock);
+        assert!(!lock_path(&config).exists());
+        let lock = ConfigLock::acquire(&config, Duration::ZERO).unwrap();
+        assert!(lock_path(&config).exists());
+        drop(lock);
+        assert!(!lock_path(&config).exists());
+    }
+}
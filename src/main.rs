@@ -1,7 +1,10 @@
+use std::process;
+
 use fuver::cli;
 
 fn main() {
     if let Err(e) = cli::main() {
         eprintln!("{}", e);
+        process::exit(e.exit_code());
     }
 }
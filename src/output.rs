@@ -0,0 +1,85 @@
+use serde::Serialize;
+
+use crate::fuver::{self, FuVer};
+
+/// Output format of the CLI
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// All components of a `FuVer` for `fuver show --output json`.
+///
+/// Components that are not defined are `null`.
+/// The keys are a documented interface for scripts. Add new keys instead of
+/// renaming or removing existing ones.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// `major.minor.patch`
+    pub version: String,
+    pub major: usize,
+    pub minor: usize,
+    pub patch: usize,
+    /// dot-separated pre-release identifiers (e.g. `rc.1`)
+    pub pre: Option<String>,
+    /// pre-release without the trailing number (e.g. `rc`)
+    pub pre_tag: Option<String>,
+    pub pre_number: Option<usize>,
+    /// build metadata formatted with the configured format
+    pub build: Option<String>,
+    pub build_number: Option<usize>,
+    /// RFC 3339
+    pub build_date: Option<String>,
+    pub build_hash: Option<String>,
+    /// full SemVer string with the formatted build metadata
+    pub full: String,
+}
+
+impl VersionInfo {
+    /// # Errors
+    /// The configured build metadata format is invalid.
+    pub fn new(fv: &FuVer) -> fuver::Result<VersionInfo> {
+        let version = fv.get_version();
+        let pre = fv.get_pre().ok();
+        let build = fv.get_build().ok();
+        let formatted = build.map(|b| b.format_string()).transpose()?;
+        Ok(VersionInfo {
+            version: version.to_string(),
+            major: version.get_major(),
+            minor: version.get_minor(),
+            patch: version.get_patch(),
+            pre: pre.map(|p| p.to_string()),
+            pre_tag: pre.map(|p| p.get_tag()),
+            pre_number: pre.and_then(|p| p.get_number()),
+            build: formatted,
+            build_number: build.map(|b| b.get_number()),
+            build_date: build.map(|b| b.get_date()),
+            build_hash: build.map(|b| b.get_hash()),
+            full: fv.full_string()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_info_json() {
+        let fv = FuVer::parse("1.2.3-rc.1+build.5").unwrap();
+        let json = serde_json::to_string(&VersionInfo::new(&fv).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":"1.2.3","major":1,"minor":2,"patch":3,"pre":"rc.1","pre_tag":"rc","pre_number":1,"build":"build.5","build_number":5,"build_date":"","build_hash":"","full":"1.2.3-rc.1+build.5"}"#
+        );
+
+        let fv = FuVer::parse("0.1.0").unwrap();
+        let json = serde_json::to_string(&VersionInfo::new(&fv).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":"0.1.0","major":0,"minor":1,"patch":0,"pre":null,"pre_tag":null,"pre_number":null,"build":null,"build_number":null,"build_date":null,"build_hash":null,"full":"0.1.0"}"#
+        );
+    }
+}
@@ -1,24 +1,111 @@
 use serde::{Deserialize, Serialize};
-use std::{fmt, result};
+use std::{cmp::Ordering, error, fmt, result, str::FromStr};
 
 use crate::identifier;
 
 /// Pre-release version information for semantic versioning
 ///
-/// consisting of a tag and an optional number (e.g., "alpha.1", "beta.2").
-#[derive(Serialize, Deserialize, Clone)]
+/// consisting of dot-separated identifiers (e.g., "alpha.1", "beta.2", "feat-login.3").
+/// A trailing numeric identifier is treated as the number and the rest as the tag.
+///
+/// Ordering follows SemVer 2.0: identifiers are compared one by one,
+/// numeric identifiers numerically and alphanumeric ones in ASCII order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(try_from = "PreReleaseToml", into = "PreReleaseToml")]
 pub struct PreRelease {
-    tag: String,
+    identifiers: Vec<String>,
+}
+
+/// TOML representation of PreRelease.
+///
+/// `tag` and `number` are used when the tag is a single identifier,
+/// which keeps existing files compatible. Otherwise `identifiers` is used.
+#[derive(Serialize, Deserialize)]
+struct PreReleaseToml {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     number: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    identifiers: Option<Vec<String>>,
+}
+
+impl TryFrom<PreReleaseToml> for PreRelease {
+    type Error = PreReleaseError;
+    fn try_from(t: PreReleaseToml) -> Result<Self> {
+        if let Some(ids) = t.identifiers {
+            return PreRelease::from_str(&ids.join("."));
+        }
+        let tag = t
+            .tag
+            .ok_or_else(|| PreReleaseError::NotDefined("tag".to_string()))?;
+        let mut pre = PreRelease::new(&tag)?;
+        if let Some(n) = t.number {
+            pre.identifiers.push(n.to_string());
+        }
+        Ok(pre)
+    }
+}
+
+impl From<PreRelease> for PreReleaseToml {
+    fn from(p: PreRelease) -> Self {
+        let (tag, number) = p.split_number();
+        if tag.len() == 1 {
+            PreReleaseToml {
+                tag: Some(tag[0].to_string()),
+                number,
+                identifiers: None,
+            }
+        } else {
+            PreReleaseToml {
+                tag: None,
+                number: None,
+                identifiers: Some(p.identifiers),
+            }
+        }
+    }
 }
 
 impl fmt::Display for PreRelease {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", &self.tag)?;
-        if let Some(n) = self.number {
-            write!(f, ".{}", n)?;
-        }
-        Ok(())
+        write!(f, "{}", self.identifiers.join("."))
+    }
+}
+
+impl FromStr for PreRelease {
+    type Err = PreReleaseError;
+
+    /// Parse dot-separated identifiers (e.g. `alpha.1.feature-x`).
+    ///
+    /// # Errors
+    /// * empty identifier or invalid character included
+    /// * numeric identifier with leading zeros
+    fn from_str(s: &str) -> Result<Self> {
+        let identifiers = split_identifiers(s)?;
+        Ok(PreRelease { identifiers })
+    }
+}
+
+impl PartialEq for PreRelease {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PreRelease {}
+
+impl PartialOrd for PreRelease {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PreRelease {
+    fn cmp(&self, other: &Self) -> Ordering {
+        identifier::compare_identifiers(
+            self.identifiers.iter().map(String::as_str),
+            other.identifiers.iter().map(String::as_str),
+        )
     }
 }
 
@@ -27,10 +114,12 @@ pub type Result<T> = result::Result<T, PreReleaseError>;
 #[derive(Debug)]
 pub enum PreReleaseError {
     Undefined,
-    Format(String),
+    Format(identifier::FormatError),
     InvalidNumber(usize),
     Overflow(usize),
     NotDefined(String),
+    UnknownStage(String),
+    LastStage(String),
 }
 
 impl fmt::Display for PreReleaseError {
@@ -43,12 +132,41 @@ impl fmt::Display for PreReleaseError {
             }
             PreReleaseError::Undefined => write!(f, "プレリリースが定義されていません"),
             PreReleaseError::NotDefined(s) => write!(f, "pre-release {} is not defined.", s),
+            PreReleaseError::UnknownStage(s) => write!(f, "{}はステージに含まれていません", s),
+            PreReleaseError::LastStage(s) => write!(f, "{}は最後のステージです", s),
         }
     }
 }
 
-fn check_identifier(tag: &str) -> Result<()> {
-    identifier::check_identifier(tag).map_err(|e| PreReleaseError::Format(e.to_string()))
+impl error::Error for PreReleaseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            PreReleaseError::Format(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<identifier::FormatError> for PreReleaseError {
+    fn from(e: identifier::FormatError) -> Self {
+        PreReleaseError::Format(e)
+    }
+}
+
+/// Default pre-release stages in order.
+pub const DEFAULT_STAGES: [&str; 3] = ["alpha", "beta", "rc"];
+
+fn split_identifiers(tag: &str) -> Result<Vec<String>> {
+    if tag.is_empty() {
+        return Err(PreReleaseError::Format(identifier::FormatError::EmptyTag));
+    }
+    tag.split(".")
+        .map(|id| {
+            identifier::check_prerelease_identifier(id)
+                .map(|_| id.to_string())
+                .map_err(PreReleaseError::Format)
+        })
+        .collect()
 }
 
 fn check_number_identifier(n: usize) -> Result<()> {
@@ -62,7 +180,7 @@ impl PreRelease {
     /// Creates a new PreRelease with tag.
     ///
     /// # Arguments
-    /// * `tag` - pre-release tag
+    /// * `tag` - pre-release tag, dot-separated identifiers are allowed
     ///
     /// # Errors
     /// Returns `FormatError`
@@ -70,11 +188,7 @@ impl PreRelease {
     /// * tag contains invalid characters
     ///
     pub fn new(tag: &str) -> Result<PreRelease> {
-        check_identifier(tag)?;
-        Ok(PreRelease {
-            tag: tag.to_string(),
-            number: None,
-        })
+        PreRelease::from_str(tag)
     }
 
     /// Creates a new PreRelease with tag and number.
@@ -83,29 +197,48 @@ impl PreRelease {
     /// * tag empty or invalid character included
     /// * number is 0
     pub fn with_number(tag: &str, number: usize) -> Result<PreRelease> {
-        check_identifier(tag)?;
         check_number_identifier(number)?;
-        Ok(PreRelease {
-            tag: tag.to_string(),
-            number: Some(number),
-        })
+        let mut pre = PreRelease::new(tag)?;
+        pre.identifiers.push(number.to_string());
+        Ok(pre)
+    }
+
+    /// tag identifiers and trailing number
+    fn split_number(&self) -> (&[String], Option<usize>) {
+        if let [tag @ .., last] = self.identifiers.as_slice() {
+            if !tag.is_empty() && identifier::is_numeric(last) {
+                if let Ok(n) = last.parse::<usize>() {
+                    return (tag, Some(n));
+                }
+            }
+        }
+        (&self.identifiers, None)
+    }
+
+    pub fn get_identifiers(&self) -> Vec<String> {
+        self.identifiers.clone()
     }
 
+    /// identifiers except the trailing number
     pub fn get_tag(&self) -> String {
-        self.tag.to_string()
+        self.split_number().0.join(".")
     }
 
+    /// trailing numeric identifier
     pub fn get_number(&self) -> Option<usize> {
-        self.number
+        self.split_number().1
     }
 
-    /// set new tag
+    /// set new tag. the number is kept.
     ///
     /// # Errors
     /// empty or invalid character included
     pub fn set_tag(&mut self, tag: &str) -> Result<()> {
-        check_identifier(tag)?;
-        self.tag = tag.to_string();
+        let mut identifiers = split_identifiers(tag)?;
+        if let Some(n) = self.get_number() {
+            identifiers.push(n.to_string());
+        }
+        self.identifiers = identifiers;
         Ok(())
     }
 
@@ -115,57 +248,66 @@ impl PreRelease {
     /// number is 0
     pub fn set_number(&mut self, number: usize) -> Result<()> {
         check_number_identifier(number)?;
-        self.number = Some(number);
+        if self.get_number().is_some() {
+            self.identifiers.pop();
+        }
+        self.identifiers.push(number.to_string());
         Ok(())
     }
 
     /// increment number
     ///
-    /// If number is None. initialize 1.
+    /// The number is the trailing numeric identifier, same as [`PreRelease::get_number`]
+    /// (`alpha.2` -> `alpha.3`). Without it, `.1` is appended
+    /// (`alpha.1.feature-x` -> `alpha.1.feature-x.1`).
     pub fn increment_number(&mut self) -> Result<()> {
-        let mut new_n = self.number.unwrap_or(0);
-        new_n = new_n
-            .checked_add(1)
-            .ok_or(PreReleaseError::Overflow(new_n))?;
-        self.number = Some(new_n);
+        match self.get_number() {
+            Some(n) => {
+                let new_n = n.checked_add(1).ok_or(PreReleaseError::Overflow(n))?;
+                self.identifiers.pop();
+                self.identifiers.push(new_n.to_string());
+            }
+            None => self.identifiers.push("1".to_string()),
+        }
         Ok(())
     }
 
+    /// Move to the next stage and reset number to 1.
+    ///
+    /// `stages` is the ordered list of tags (e.g. `alpha`, `beta`, `rc`).
+    ///
+    /// # Errors
+    /// * current tag is not in `stages`
+    /// * current tag is the last stage
+    /// * next tag is empty or invalid character included
+    pub fn next_stage<S: AsRef<str>>(&mut self, stages: &[S]) -> Result<()> {
+        let tag = self.get_tag();
+        let index = stages
+            .iter()
+            .position(|s| s.as_ref() == tag)
+            .ok_or_else(|| PreReleaseError::UnknownStage(tag.to_string()))?;
+        let next = stages
+            .get(index + 1)
+            .ok_or_else(|| PreReleaseError::LastStage(tag.to_string()))?;
+        self.set(next.as_ref(), Some(1))
+    }
+
     /// set tag and number
     ///
     /// # Errors
     /// tag empty or invalid character included
     /// number is 0
     pub fn set(&mut self, tag: &str, number: Option<usize>) -> Result<()> {
-        check_identifier(tag)?;
-        self.set_tag(tag)?;
-
+        let mut identifiers = split_identifiers(tag)?;
         if let Some(n) = number {
             check_number_identifier(n)?;
-            self.set_number(n)?;
         }
-        Ok(())
-    }
-
-    /// print pre-release info
-    pub fn show(&self) -> Result<()> {
-        println!("{}", self);
-        Ok(())
-    }
 
-    pub fn show_tag(&self) -> Result<()> {
-        println!("{}", &self.tag);
-        Ok(())
-    }
-
-    pub fn show_number(&self) -> Result<()> {
-        match self.number.as_ref() {
-            Some(n) => {
-                println!("{}", n);
-                Ok(())
-            }
-            None => Err(PreReleaseError::NotDefined("number".to_string())),
+        if let Some(n) = number.or(self.get_number()) {
+            identifiers.push(n.to_string());
         }
+        self.identifiers = identifiers;
+        Ok(())
     }
 }
 
@@ -184,24 +326,24 @@ mod tests {
     #[test]
     fn test_default_prerelease() {
         let pre = PreRelease::default();
-        assert_eq!(pre.tag, "alpha");
-        assert_eq!(pre.number, None);
+        assert_eq!(pre.get_tag(), "alpha");
+        assert_eq!(pre.get_number(), None);
         assert_eq!(pre.to_string(), "alpha");
     }
 
     #[test]
     fn test_new_prerelease() {
         let pre = PreRelease::new("beta").unwrap();
-        assert_eq!(pre.tag, "beta");
-        assert_eq!(pre.number, None);
+        assert_eq!(pre.get_tag(), "beta");
+        assert_eq!(pre.get_number(), None);
         assert_eq!(pre.to_string(), "beta");
     }
 
     #[test]
     fn test_with_number() {
         let pre = PreRelease::with_number("rc", 1).unwrap();
-        assert_eq!(pre.tag, "rc");
-        assert_eq!(pre.number, Some(1));
+        assert_eq!(pre.get_tag(), "rc");
+        assert_eq!(pre.get_number(), Some(1));
         assert_eq!(pre.to_string(), "rc.1");
     }
 
@@ -222,7 +364,7 @@ mod tests {
     fn test_set_tag() {
         let mut pre = PreRelease::default();
         pre.set_tag("beta").unwrap();
-        assert_eq!(pre.tag, "beta");
+        assert_eq!(pre.get_tag(), "beta");
         assert_eq!(pre.to_string(), "beta");
     }
 
@@ -232,14 +374,14 @@ mod tests {
         let result = pre.set_tag("");
         assert!(result.is_err());
 
-        assert_eq!(pre.tag, "alpha");
+        assert_eq!(pre.get_tag(), "alpha");
     }
 
     #[test]
     fn test_set_number() {
         let mut pre = PreRelease::default();
         pre.set_number(3).unwrap();
-        assert_eq!(pre.number, Some(3));
+        assert_eq!(pre.get_number(), Some(3));
         assert_eq!(pre.to_string(), "alpha.3");
     }
 
@@ -252,16 +394,16 @@ mod tests {
             _ => panic!("Expected InvalidNumber error"),
         }
 
-        assert_eq!(pre.number, None);
+        assert_eq!(pre.get_number(), None);
     }
 
     #[test]
     fn test_increment_number_from_none() {
         let mut pre = PreRelease::new("beta").unwrap();
-        assert_eq!(pre.number, None);
+        assert_eq!(pre.get_number(), None);
 
         pre.increment_number().unwrap();
-        assert_eq!(pre.number, Some(1));
+        assert_eq!(pre.get_number(), Some(1));
         assert_eq!(pre.to_string(), "beta.1");
     }
 
@@ -269,7 +411,7 @@ mod tests {
     fn test_increment_number() {
         let mut pre = PreRelease::with_number("rc", 1).unwrap();
         pre.increment_number().unwrap();
-        assert_eq!(pre.number, Some(2));
+        assert_eq!(pre.get_number(), Some(2));
         assert_eq!(pre.to_string(), "rc.2");
     }
 
@@ -283,15 +425,15 @@ mod tests {
             _ => panic!("Expected Overflow error"),
         }
 
-        assert_eq!(pre.number, Some(usize::MAX));
+        assert_eq!(pre.get_number(), Some(usize::MAX));
     }
 
     #[test]
     fn test_set() {
         let mut pre = PreRelease::default();
         pre.set("rc", Some(2)).unwrap();
-        assert_eq!(pre.tag, "rc");
-        assert_eq!(pre.number, Some(2));
+        assert_eq!(pre.get_tag(), "rc");
+        assert_eq!(pre.get_number(), Some(2));
         assert_eq!(pre.to_string(), "rc.2");
     }
 
@@ -305,8 +447,8 @@ mod tests {
         let result = pre.set("beta", Some(0));
         assert!(result.is_err());
 
-        assert_eq!(pre.tag, "alpha");
-        assert_eq!(pre.number, None);
+        assert_eq!(pre.get_tag(), "alpha");
+        assert_eq!(pre.get_number(), None);
     }
 
     #[test]
@@ -321,8 +463,11 @@ mod tests {
 
     #[test]
     fn test_error_display() {
-        let err = PreReleaseError::Format("test".to_string());
-        assert_eq!(format!("{}", err), "フォーマットが不正です: test");
+        let err = PreReleaseError::Format(identifier::FormatError::EmptyTag);
+        assert_eq!(
+            format!("{}", err),
+            "フォーマットが不正です: 空文字列は対応していません"
+        );
 
         let err = PreReleaseError::InvalidNumber(0);
         assert_eq!(format!("{}", err), "数値に0は指定できません");
@@ -334,4 +479,122 @@ mod tests {
             "数値が指定できる範囲を超えています: 100+1"
         );
     }
+
+    #[test]
+    fn test_ordering() {
+        let alpha = PreRelease::new("alpha").unwrap();
+        let alpha1 = PreRelease::with_number("alpha", 1).unwrap();
+        let alpha2 = PreRelease::with_number("alpha", 2).unwrap();
+        let alpha10 = PreRelease::with_number("alpha", 10).unwrap();
+        let beta = PreRelease::new("beta").unwrap();
+        let rc1 = PreRelease::with_number("rc", 1).unwrap();
+
+        assert!(alpha < alpha1);
+        assert!(alpha1 < alpha2);
+        assert!(alpha2 < alpha10);
+        assert!(alpha10 < beta);
+        assert!(beta < rc1);
+        assert_eq!(alpha1, PreRelease::with_number("alpha", 1).unwrap());
+    }
+
+    #[test]
+    fn test_ordering_numeric_tag() {
+        // 数値の識別子は英数字の識別子より低い
+        let numeric = PreRelease::new("2").unwrap();
+        let alpha = PreRelease::new("alpha").unwrap();
+        assert!(numeric < alpha);
+
+        // 数値の識別子同士は数値として比較する
+        let ten = PreRelease::new("10").unwrap();
+        assert!(numeric < ten);
+    }
+
+    #[test]
+    fn test_next_stage() {
+        let mut pre = PreRelease::with_number("alpha", 3).unwrap();
+        pre.next_stage(&DEFAULT_STAGES).unwrap();
+        assert_eq!(pre.to_string(), "beta.1");
+
+        pre.next_stage(&DEFAULT_STAGES).unwrap();
+        assert_eq!(pre.to_string(), "rc.1");
+
+        match pre.next_stage(&DEFAULT_STAGES) {
+            Err(PreReleaseError::LastStage(s)) => assert_eq!(s, "rc"),
+            _ => panic!("Expected LastStage error"),
+        }
+        assert_eq!(pre.to_string(), "rc.1");
+    }
+
+    #[test]
+    fn test_next_stage_custom() {
+        let stages = vec!["dev".to_string(), "preview".to_string()];
+        let mut pre = PreRelease::new("dev").unwrap();
+        pre.next_stage(&stages).unwrap();
+        assert_eq!(pre.to_string(), "preview.1");
+
+        let mut pre = PreRelease::new("alpha").unwrap();
+        match pre.next_stage(&stages) {
+            Err(PreReleaseError::UnknownStage(s)) => assert_eq!(s, "alpha"),
+            _ => panic!("Expected UnknownStage error"),
+        }
+    }
+
+    #[test]
+    fn test_multi_identifiers() {
+        let pre = PreRelease::from_str("alpha.1.feature-x").unwrap();
+        assert_eq!(pre.to_string(), "alpha.1.feature-x");
+        assert_eq!(pre.get_tag(), "alpha.1.feature-x");
+        assert_eq!(pre.get_number(), None);
+
+        let pre = PreRelease::from_str("feat-login.3").unwrap();
+        assert_eq!(pre.get_tag(), "feat-login");
+        assert_eq!(pre.get_number(), Some(3));
+
+        assert!(PreRelease::from_str("alpha..1").is_err());
+        assert!(PreRelease::from_str("alpha.01").is_err());
+    }
+
+    #[test]
+    fn test_increment_multi_identifiers() {
+        // 番号は末尾の数値の識別子だけ。途中の数値は番号として扱わない
+        let mut pre = PreRelease::from_str("alpha.1.feature-x").unwrap();
+        assert_eq!(pre.get_number(), None);
+        pre.increment_number().unwrap();
+        assert_eq!(pre.to_string(), "alpha.1.feature-x.1");
+        assert_eq!(pre.get_number(), Some(1));
+        assert_eq!(pre.get_tag(), "alpha.1.feature-x");
+        pre.increment_number().unwrap();
+        assert_eq!(pre.to_string(), "alpha.1.feature-x.2");
+
+        // 数値の識別子がなければ`.1`を追加する
+        let mut pre = PreRelease::from_str("alpha.feature-x").unwrap();
+        pre.increment_number().unwrap();
+        assert_eq!(pre.to_string(), "alpha.feature-x.1");
+    }
+
+    #[test]
+    fn test_set_multi_identifiers() {
+        let mut pre = PreRelease::from_str("feat-login.3").unwrap();
+        pre.set_tag("feat.login").unwrap();
+        assert_eq!(pre.to_string(), "feat.login.3");
+
+        pre.set_number(4).unwrap();
+        assert_eq!(pre.to_string(), "feat.login.4");
+    }
+
+    #[test]
+    fn test_toml_compat() {
+        let pre: PreRelease = toml::from_str("tag = \"beta\"\nnumber = 2").unwrap();
+        assert_eq!(pre.to_string(), "beta.2");
+        assert_eq!(
+            toml::to_string(&pre).unwrap(),
+            "tag = \"beta\"\nnumber = 2\n"
+        );
+
+        let pre = PreRelease::from_str("alpha.1.feature-x").unwrap();
+        let s = toml::to_string(&pre).unwrap();
+        assert_eq!(s, "identifiers = [\"alpha\", \"1\", \"feature-x\"]\n");
+        let pre: PreRelease = toml::from_str(&s).unwrap();
+        assert_eq!(pre.to_string(), "alpha.1.feature-x");
+    }
 }
@@ -0,0 +1,581 @@
+use std::{fs, path::Path, str::FromStr};
+
+use crate::document;
+use crate::fuver::{self, FuVer, FuVerError};
+use crate::sync;
+
+pub const CARGO_MANIFEST: &str = "Cargo.toml";
+pub const PYPROJECT: &str = "pyproject.toml";
+pub const PACKAGE_JSON: &str = "package.json";
+
+/// Where `FuVer` is persisted.
+pub trait Storage {
+    /// Load `FuVer` from `path`.
+    fn load(&self, path: &Path) -> fuver::Result<FuVer>;
+
+    /// Contents of `path` with `fv` written in. The rest of the file is left untouched.
+    fn render(&self, fv: &FuVer, path: &Path) -> fuver::Result<String>;
+
+    /// `FuVer` to initialize `path` with.
+    ///
+    /// # Errors
+    /// `path` is already initialized.
+    fn initial(&self, path: &Path) -> fuver::Result<FuVer>;
+
+    /// Save `fv` to `path`.
+    fn save(&self, fv: &FuVer, path: &Path) -> fuver::Result<()> {
+        let s = self.render(fv, path)?;
+        fuver::write_atomic(path, s.as_bytes())?;
+        Ok(())
+    }
+
+    /// Add an initial `FuVer` to `path`.
+    fn init(&self, path: &Path) -> fuver::Result<FuVer> {
+        let fv = self.initial(path)?;
+        self.save(&fv, path)?;
+        Ok(fv)
+    }
+}
+
+/// Select the storage by the file name of `path`.
+///
+/// | file name        | storage                                        |
+/// | ---------------- | ---------------------------------------------- |
+/// | `Cargo.toml`     | `[package.metadata.fuver]`, `package.version`  |
+/// | `pyproject.toml` | `[tool.fuver]`, `project.version`              |
+/// | `package.json`   | `fuver`, `version`                             |
+/// | others           | the whole file (`fuver.toml`)                  |
+pub fn for_path(path: &Path) -> Box<dyn Storage> {
+    match path.file_name().and_then(|n| n.to_str()) {
+        Some(CARGO_MANIFEST) => Box::new(CargoManifest),
+        Some(PYPROJECT) => Box::new(PyProject),
+        Some(PACKAGE_JSON) => Box::new(PackageJson),
+        _ => Box::new(FuVerToml),
+    }
+}
+
+/// `fuver.toml`
+pub struct FuVerToml;
+
+impl Storage for FuVerToml {
+    fn load(&self, path: &Path) -> fuver::Result<FuVer> {
+        FuVer::from_str(&fs::read_to_string(path)?)
+    }
+
+    fn render(&self, fv: &FuVer, path: &Path) -> fuver::Result<String> {
+        fv.render(path)
+    }
+
+    fn initial(&self, path: &Path) -> fuver::Result<FuVer> {
+        if path.exists() {
+            return Err(FuVerError::InitError("Already initialized.".to_string()));
+        }
+        Ok(FuVer::default())
+    }
+
+    fn save(&self, fv: &FuVer, path: &Path) -> fuver::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fv.save(path)
+    }
+}
+
+/// Locations of the fuver table and the project version in a TOML manifest.
+struct TomlPaths {
+    metadata: &'static [&'static str],
+    version: &'static [&'static str],
+}
+
+fn lookup<'a>(table: &'a toml::Table, path: &[&str]) -> Option<&'a toml::Value> {
+    let (last, parents) = path.split_last()?;
+    parents
+        .iter()
+        .try_fold(table, |t, key| t.get(*key).and_then(|v| v.as_table()))?
+        .get(*last)
+}
+
+fn lookup_table<'a>(table: &'a toml::Table, path: &[&str]) -> Option<&'a toml::Table> {
+    lookup(table, path).and_then(|v| v.as_table())
+}
+
+fn read_toml(path: &Path) -> fuver::Result<(String, toml::Table)> {
+    let s = fs::read_to_string(path)?;
+    let manifest = toml::from_str(&s)?;
+    Ok((s, manifest))
+}
+
+fn load_toml(manifest: &toml::Table, paths: &TomlPaths) -> fuver::Result<FuVer> {
+    let table = lookup_table(manifest, paths.metadata).ok_or_else(|| {
+        FuVerError::Manifest(format!("[{}] is not defined", paths.metadata.join(".")))
+    })?;
+    Ok(toml::Value::Table(table.clone()).try_into()?)
+}
+
+/// Write `fv` to the fuver table, keeping `options` of the old table.
+/// With `update_version`, the project version is also set.
+fn render_toml(
+    fv: &FuVer,
+    path: &Path,
+    paths: &TomlPaths,
+    options: &[&str],
+    update_version: bool,
+) -> fuver::Result<String> {
+    let (s, manifest) = read_toml(path)?;
+    let mut doc: toml_edit::DocumentMut = s.parse()?;
+
+    let new: toml_edit::DocumentMut = toml::to_string(fv)?.parse()?;
+    let mut new = new.as_table().clone();
+    if let Some(old) = lookup_table(&manifest, paths.metadata) {
+        for key in options {
+            if let Some(v) = old.get(*key) {
+                let value: toml_edit::Value = v.to_string().parse()?;
+                new.insert(key, toml_edit::Item::Value(value));
+            }
+        }
+    }
+    if !document::merge_at(&mut doc, paths.metadata, &new) {
+        return Err(FuVerError::Manifest(format!(
+            "{} is not a table",
+            paths.metadata.join(".")
+        )));
+    }
+    if update_version && !document::set_string(&mut doc, paths.version, &fv.core_string()) {
+        return Err(FuVerError::Manifest(format!(
+            "{} is not a string",
+            paths.version.join(".")
+        )));
+    }
+    Ok(doc.to_string())
+}
+
+/// Initial `FuVer` from the current project version, or the default.
+fn initial(version: Option<&str>) -> FuVer {
+    version
+        .and_then(|v| FuVer::parse(v).ok())
+        .unwrap_or_default()
+}
+
+/// `Cargo.toml` (`[package.metadata.fuver]` or `[workspace.metadata.fuver]`)
+///
+/// With `package-version = true` in the table, `package.version`
+/// (or `workspace.package.version`) is also updated.
+pub struct CargoManifest;
+
+/// Key in `[package.metadata.fuver]` to also update `package.version`.
+const PACKAGE_VERSION_KEY: &str = "package-version";
+
+const CARGO_PACKAGE: TomlPaths = TomlPaths {
+    metadata: &["package", "metadata", "fuver"],
+    version: &["package", "version"],
+};
+
+const CARGO_WORKSPACE: TomlPaths = TomlPaths {
+    metadata: &["workspace", "metadata", "fuver"],
+    version: &["workspace", "package", "version"],
+};
+
+impl CargoManifest {
+    fn paths(manifest: &toml::Table) -> fuver::Result<&'static TomlPaths> {
+        if manifest.contains_key("package") {
+            Ok(&CARGO_PACKAGE)
+        } else if manifest.contains_key("workspace") {
+            Ok(&CARGO_WORKSPACE)
+        } else {
+            Err(FuVerError::Manifest(
+                "[package] or [workspace] is required".to_string(),
+            ))
+        }
+    }
+}
+
+impl Storage for CargoManifest {
+    fn load(&self, path: &Path) -> fuver::Result<FuVer> {
+        let (_, manifest) = read_toml(path)?;
+        load_toml(&manifest, CargoManifest::paths(&manifest)?)
+    }
+
+    fn render(&self, fv: &FuVer, path: &Path) -> fuver::Result<String> {
+        let (_, manifest) = read_toml(path)?;
+        let paths = CargoManifest::paths(&manifest)?;
+        let package_version = lookup_table(&manifest, paths.metadata)
+            .and_then(|t| t.get(PACKAGE_VERSION_KEY))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        render_toml(fv, path, paths, &[PACKAGE_VERSION_KEY], package_version)
+    }
+
+    fn initial(&self, path: &Path) -> fuver::Result<FuVer> {
+        let (_, manifest) = read_toml(path)?;
+        let paths = CargoManifest::paths(&manifest)?;
+        if lookup(&manifest, paths.metadata).is_some() {
+            return Err(FuVerError::InitError("Already initialized.".to_string()));
+        }
+        Ok(initial(
+            lookup(&manifest, paths.version).and_then(|v| v.as_str()),
+        ))
+    }
+}
+
+/// `pyproject.toml` (`[tool.fuver]`)
+///
+/// `project.version` is also updated unless it is listed in `project.dynamic`.
+pub struct PyProject;
+
+const PYPROJECT_PATHS: TomlPaths = TomlPaths {
+    metadata: &["tool", "fuver"],
+    version: &["project", "version"],
+};
+
+impl PyProject {
+    fn has_static_version(manifest: &toml::Table) -> bool {
+        let Some(project) = lookup_table(manifest, &["project"]) else {
+            return false;
+        };
+        let dynamic = project
+            .get("dynamic")
+            .and_then(|v| v.as_array())
+            .is_some_and(|a| a.iter().any(|v| v.as_str() == Some("version")));
+        !dynamic
+    }
+}
+
+impl Storage for PyProject {
+    fn load(&self, path: &Path) -> fuver::Result<FuVer> {
+        let (_, manifest) = read_toml(path)?;
+        load_toml(&manifest, &PYPROJECT_PATHS)
+    }
+
+    fn render(&self, fv: &FuVer, path: &Path) -> fuver::Result<String> {
+        let (_, manifest) = read_toml(path)?;
+        let update_version = PyProject::has_static_version(&manifest);
+        render_toml(fv, path, &PYPROJECT_PATHS, &[], update_version)
+    }
+
+    fn initial(&self, path: &Path) -> fuver::Result<FuVer> {
+        let (_, manifest) = read_toml(path)?;
+        if lookup(&manifest, PYPROJECT_PATHS.metadata).is_some() {
+            return Err(FuVerError::InitError("Already initialized.".to_string()));
+        }
+        Ok(initial(
+            lookup(&manifest, PYPROJECT_PATHS.version).and_then(|v| v.as_str()),
+        ))
+    }
+}
+
+/// `package.json` (`fuver` and `version`)
+///
+/// Only the values of `version` and `fuver` are rewritten in place; the rest
+/// of the file is kept byte for byte. Missing keys are appended to the end of
+/// the root object, and `fuver` is written with the indentation of the file.
+pub struct PackageJson;
+
+const PACKAGE_JSON_KEY: &str = "fuver";
+
+impl PackageJson {
+    fn read(path: &Path) -> fuver::Result<(String, serde_json::Map<String, serde_json::Value>)> {
+        let s = fs::read_to_string(path)?;
+        match serde_json::from_str(&s)? {
+            serde_json::Value::Object(map) => Ok((s, map)),
+            _ => Err(FuVerError::Manifest(
+                "package.json is not an object".to_string(),
+            )),
+        }
+    }
+
+    /// Indentation of the first nested line, or 2 spaces.
+    fn indent(s: &str) -> &str {
+        s.lines()
+            .nth(1)
+            .map(|l| &l[..l.len() - l.trim_start().len()])
+            .filter(|i| !i.is_empty())
+            .unwrap_or("  ")
+    }
+
+    /// Replace the value of the root `key` with `value` (JSON text), or
+    /// append the key to the root object.
+    fn set_key(s: &str, key: &str, value: &str, indent: &str) -> fuver::Result<String> {
+        if let Some(range) = sync::json_span(s, &format!("/{}", key)) {
+            return Ok(sync::splice(s, range, value));
+        }
+        let not_object = || FuVerError::Manifest("package.json is not an object".to_string());
+        let close = s.rfind('}').ok_or_else(not_object)?;
+        let body = s[..close].trim_end();
+        let entry = format!("\n{}{}: {}", indent, serde_json::to_string(key)?, value);
+        let (at, insert) = match body.chars().last() {
+            Some('{') => (body.len(), format!("{}\n", entry)),
+            Some(_) => (body.len(), format!(",{}", entry)),
+            None => return Err(not_object()),
+        };
+        Ok(format!("{}{}{}", &s[..at], insert, &s[at..]))
+    }
+}
+
+impl Storage for PackageJson {
+    fn load(&self, path: &Path) -> fuver::Result<FuVer> {
+        let (_, mut map) = PackageJson::read(path)?;
+        let value = map.remove(PACKAGE_JSON_KEY).ok_or_else(|| {
+            FuVerError::Manifest(format!("\"{}\" is not defined", PACKAGE_JSON_KEY))
+        })?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    fn render(&self, fv: &FuVer, path: &Path) -> fuver::Result<String> {
+        let (s, _) = PackageJson::read(path)?;
+        let indent = PackageJson::indent(&s).to_string();
+
+        let mut out = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        let mut ser = serde_json::Serializer::with_formatter(&mut out, formatter);
+        serde::Serialize::serialize(fv, &mut ser)?;
+        // `fuver`はルート直下なので2行目以降を1段下げる
+        let table = String::from_utf8_lossy(&out).replace('\n', &format!("\n{}", indent));
+
+        let version = serde_json::to_string(&fv.core_string())?;
+        let s = PackageJson::set_key(&s, "version", &version, &indent)?;
+        PackageJson::set_key(&s, PACKAGE_JSON_KEY, &table, &indent)
+    }
+
+    fn initial(&self, path: &Path) -> fuver::Result<FuVer> {
+        let (_, map) = PackageJson::read(path)?;
+        if map.contains_key(PACKAGE_JSON_KEY) {
+            return Err(FuVerError::InitError("Already initialized.".to_string()));
+        }
+        Ok(initial(map.get("version").and_then(|v| v.as_str())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, path::PathBuf, process};
+
+    fn temp_file(dir: &str, name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("fuver-storage-{}-{}", dir, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn cleanup(path: &Path) {
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_cargo_package() {
+        let path = temp_file(
+            "package",
+            CARGO_MANIFEST,
+            r#"[package]
+name = "app"
+version = "1.2.3-rc.1" # fuverで更新
+
+[package.metadata.fuver]
+package-version = true
+
+[package.metadata.fuver.version]
+major = 1
+minor = 2
+patch = 3
+
+[package.metadata.fuver.pre]
+tag = "rc"
+number = 1
+
+[dependencies]
+serde = "1"
+"#,
+        );
+
+        let storage = for_path(&path);
+        let mut fv = storage.load(&path).unwrap();
+        assert_eq!(fv, FuVer::parse("1.2.3-rc.1").unwrap());
+
+        fv.release(None, None, false).unwrap();
+        storage.save(&fv, &path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            r#"[package]
+name = "app"
+version = "1.2.3" # fuverで更新
+
+[package.metadata.fuver]
+package-version = true
+
+[package.metadata.fuver.version]
+major = 1
+minor = 2
+patch = 3
+str = "1.2.3"
+
+[dependencies]
+serde = "1"
+"#
+        );
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_cargo_workspace() {
+        let original = r#"[workspace]
+members = ["a", "b"]
+
+[workspace.package]
+version = "0.3.1"
+"#;
+        let path = temp_file("workspace", CARGO_MANIFEST, original);
+
+        assert!(matches!(
+            CargoManifest.load(&path),
+            Err(FuVerError::Manifest(_))
+        ));
+        let fv = CargoManifest.init(&path).unwrap();
+        assert_eq!(fv, FuVer::parse("0.3.1").unwrap());
+        assert!(matches!(
+            CargoManifest.init(&path),
+            Err(FuVerError::InitError(_))
+        ));
+
+        let s = fs::read_to_string(&path).unwrap();
+        assert!(s.starts_with(original));
+        assert!(s.contains("[workspace.metadata.fuver.version]"));
+        assert_eq!(CargoManifest.load(&path).unwrap(), fv);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_cargo_inherited_version() {
+        let path = temp_file(
+            "inherited",
+            CARGO_MANIFEST,
+            "[package]\nname = \"a\"\nversion.workspace = true\n\n[package.metadata.fuver]\npackage-version = true\n",
+        );
+        let fv = FuVer::parse("1.0.0").unwrap();
+        assert!(matches!(
+            CargoManifest.save(&fv, &path),
+            Err(FuVerError::Manifest(_))
+        ));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_pyproject() {
+        let path = temp_file(
+            "pyproject",
+            PYPROJECT,
+            r#"[project]
+name = "app"
+version = "2.0.0" # fuverで更新
+dependencies = []
+
+[tool.ruff]
+line-length = 100
+"#,
+        );
+
+        let storage = for_path(&path);
+        let mut fv = storage.init(&path).unwrap();
+        fv.incr_preminor(None).unwrap();
+        storage.save(&fv, &path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            r#"[project]
+name = "app"
+version = "2.1.0-alpha.1" # fuverで更新
+dependencies = []
+
+[tool.ruff]
+line-length = 100
+
+[tool.fuver.version]
+major = 2
+minor = 1
+patch = 0
+str = "2.1.0"
+
+[tool.fuver.pre]
+tag = "alpha"
+number = 1
+"#
+        );
+        assert_eq!(storage.load(&path).unwrap(), fv);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_pyproject_dynamic_version() {
+        let original = "[project]\nname = \"app\"\ndynamic = [\"version\"]\n";
+        let path = temp_file("dynamic", PYPROJECT, original);
+
+        PyProject.init(&path).unwrap();
+        let s = fs::read_to_string(&path).unwrap();
+        assert!(s.starts_with(original));
+        assert!(!s.contains("\nversion ="));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_package_json() {
+        let path = temp_file(
+            "json",
+            PACKAGE_JSON,
+            "{\n    \"name\": \"app\",\n    \"version\": \"1.0.0\",\n    \"scripts\": {\n        \"build\": \"tsc\"\n    }\n}\n",
+        );
+
+        let storage = for_path(&path);
+        assert!(matches!(storage.load(&path), Err(FuVerError::Manifest(_))));
+        let mut fv = storage.init(&path).unwrap();
+        fv.incr_ver_patch().unwrap();
+        storage.save(&fv, &path).unwrap();
+
+        let s = fs::read_to_string(&path).unwrap();
+        assert!(s.starts_with(
+            "{\n    \"name\": \"app\",\n    \"version\": \"1.0.1\",\n    \"scripts\": {\n        \"build\": \"tsc\"\n    },\n    \"fuver\": {\n"
+        ));
+        assert!(s.ends_with("}\n"));
+        assert_eq!(storage.load(&path).unwrap(), fv);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_package_json_in_place() {
+        let original =
+            "{\"name\": \"caf\\u00e9\", \"files\": [\"a\", \"b\"],\n  \"version\": \"1.0.0\"}";
+        let path = temp_file("json-in-place", PACKAGE_JSON, original);
+
+        let storage = for_path(&path);
+        let mut fv = storage.init(&path).unwrap();
+        let s = fs::read_to_string(&path).unwrap();
+        assert!(
+            s.starts_with("{\"name\": \"caf\\u00e9\", \"files\": [\"a\", \"b\"],\n  \"version\": \"1.0.0\",\n  \"fuver\": {\n    \"version\": {"),
+            "{}",
+            s
+        );
+        assert!(s.ends_with("\n  }}"), "{}", s);
+
+        fv.incr_ver_minor().unwrap();
+        let before = fs::read_to_string(&path).unwrap();
+        storage.save(&fv, &path).unwrap();
+        let after = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            after
+                .replace("\"1.1.0\"", "\"1.0.0\"")
+                .replace("\"minor\": 1", "\"minor\": 0"),
+            before
+        );
+        assert_eq!(storage.load(&path).unwrap(), fv);
+
+        let empty = temp_file("json-empty", PACKAGE_JSON, "{}\n");
+        storage.init(&empty).unwrap();
+        let s = fs::read_to_string(&empty).unwrap();
+        assert!(
+            s.starts_with("{\n  \"version\": \"0.1.0\",\n  \"fuver\": {"),
+            "{}",
+            s
+        );
+        assert!(s.ends_with("}\n}\n"), "{}", s);
+        cleanup(&path);
+        cleanup(&empty);
+    }
+}
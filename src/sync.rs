@@ -0,0 +1,571 @@
+use std::{
+    error, fmt, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    result,
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::document;
+
+pub type Result<T> = result::Result<T, SyncError>;
+
+#[derive(Debug)]
+pub enum SyncError {
+    IO(PathBuf, io::Error),
+    Toml(PathBuf, toml_edit::TomlError),
+    Json(PathBuf, serde_json::Error),
+    Regex(regex::Error),
+    NoCaptureGroup(String),
+    NotFound(PathBuf, Locator),
+    NotString(PathBuf, Locator),
+    OutOfDate(Vec<PathBuf>),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::IO(p, e) => write!(f, "{}: {}", p.display(), e),
+            SyncError::Toml(p, e) => write!(f, "{}: {}", p.display(), e),
+            SyncError::Json(p, e) => write!(f, "{}: {}", p.display(), e),
+            SyncError::Regex(e) => write!(f, "正規表現が不正です: {}", e),
+            SyncError::NoCaptureGroup(r) => {
+                write!(f, "正規表現にキャプチャグループがありません: {}", r)
+            }
+            SyncError::NotFound(p, l) => write!(f, "{}: {} が見つかりません", p.display(), l),
+            SyncError::NotString(p, l) => {
+                write!(f, "{}: {} が文字列ではありません", p.display(), l)
+            }
+            SyncError::OutOfDate(files) => {
+                let files: Vec<String> = files.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "バージョンが同期されていません: {}", files.join(", "))
+            }
+        }
+    }
+}
+
+impl error::Error for SyncError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SyncError::IO(_, e) => Some(e),
+            SyncError::Toml(_, e) => Some(e),
+            SyncError::Json(_, e) => Some(e),
+            SyncError::Regex(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// `[sync]` section
+///
+/// ```toml
+/// [sync]
+/// targets = [
+///     { file = "Cargo.toml", toml = "package.version" },
+///     { file = "package.json", json = "/version" },
+///     { file = "src/version.h", regex = '#define VERSION "(.*)"' },
+/// ]
+/// ```
+///
+/// Files are relative to the directory of the config file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub targets: Vec<SyncTarget>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SyncTarget {
+    pub file: PathBuf,
+    #[serde(flatten)]
+    pub locator: Locator,
+}
+
+/// Location of the version in a target file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locator {
+    /// Dotted key path (e.g. `package.version`)
+    Toml(String),
+    /// JSON pointer (e.g. `/version`)
+    Json(String),
+    /// Regex with a capture group around the version. Every match is updated.
+    Regex(String),
+}
+
+impl fmt::Display for Locator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Locator::Toml(k) => write!(f, "toml:{}", k),
+            Locator::Json(p) => write!(f, "json:{}", p),
+            Locator::Regex(r) => write!(f, "regex:{}", r),
+        }
+    }
+}
+
+/// Result of a sync target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncResult {
+    pub file: PathBuf,
+    pub locator: Locator,
+    pub old: String,
+    pub new: String,
+}
+
+impl SyncResult {
+    pub fn is_changed(&self) -> bool {
+        self.old != self.new
+    }
+}
+
+impl fmt::Display for SyncResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_changed() {
+            write!(
+                f,
+                "Sync {} ({}) {} -> {}",
+                self.file.display(),
+                self.locator,
+                self.old,
+                self.new
+            )
+        } else {
+            write!(f, "Up to date {} ({})", self.file.display(), self.locator)
+        }
+    }
+}
+
+/// Planned edit of a file, covering every target in it
+#[derive(Debug, Clone)]
+pub struct SyncEdit {
+    /// results of the targets in this file, in config order
+    pub results: Vec<SyncResult>,
+    /// `file` joined to the base directory
+    pub path: PathBuf,
+    pub before: String,
+    pub after: String,
+}
+
+impl SyncConfig {
+    /// Read every target and compute the edits, without writing.
+    ///
+    /// Targets in the same file are applied in turn to one edit, so the file
+    /// is written once with all of them.
+    ///
+    /// # Errors
+    /// * a target file cannot be read
+    /// * the locator does not point to a string
+    pub fn plan(&self, base: &Path, version: &str) -> Result<Vec<SyncEdit>> {
+        let mut edits: Vec<SyncEdit> = Vec::new();
+        for target in &self.targets {
+            let path = base.join(&target.file);
+            let index = match edits.iter().position(|e| e.path == path) {
+                Some(i) => i,
+                None => {
+                    let before =
+                        fs::read_to_string(&path).map_err(|e| SyncError::IO(path.clone(), e))?;
+                    edits.push(SyncEdit {
+                        results: Vec::new(),
+                        path: path.clone(),
+                        after: before.clone(),
+                        before,
+                    });
+                    edits.len() - 1
+                }
+            };
+            let edit = &mut edits[index];
+            let (old, after) = target.locator.replace(&path, &edit.after, version)?;
+            edit.after = after;
+            edit.results.push(SyncResult {
+                file: target.file.clone(),
+                locator: target.locator.clone(),
+                old,
+                new: version.to_string(),
+            });
+        }
+        Ok(edits)
+    }
+
+    /// Write `version` to every target. With `check`, no file is written.
+    ///
+    /// All targets are checked before writing, so a broken target leaves
+    /// every file untouched.
+    ///
+    /// # Errors
+    /// * a target file cannot be read or written
+    /// * the locator does not point to a string
+    /// * `check` and a target is out of date ([`SyncError::OutOfDate`])
+    pub fn sync(&self, base: &Path, version: &str, check: bool) -> Result<Vec<SyncResult>> {
+        let edits = self.plan(base, version)?;
+
+        if check {
+            let outdated: Vec<PathBuf> = edits
+                .iter()
+                .filter(|e| e.results.iter().any(SyncResult::is_changed))
+                .map(|e| e.results[0].file.clone())
+                .collect();
+            if !outdated.is_empty() {
+                return Err(SyncError::OutOfDate(outdated));
+            }
+        } else {
+            for edit in edits.iter().filter(|e| e.before != e.after) {
+                crate::fuver::write_atomic(&edit.path, edit.after.as_bytes())
+                    .map_err(|e| SyncError::IO(edit.path.clone(), e))?;
+            }
+        }
+        Ok(edits.into_iter().flat_map(|e| e.results).collect())
+    }
+}
+
+impl Locator {
+    /// Current value and the text with `version` written in.
+    fn replace(&self, path: &Path, text: &str, version: &str) -> Result<(String, String)> {
+        let not_found = || SyncError::NotFound(path.to_path_buf(), self.clone());
+        let not_string = || SyncError::NotString(path.to_path_buf(), self.clone());
+        match self {
+            Locator::Toml(key) => {
+                let mut doc: toml_edit::DocumentMut = text
+                    .parse()
+                    .map_err(|e| SyncError::Toml(path.to_path_buf(), e))?;
+                let keys: Vec<&str> = key.split('.').collect();
+                let item = keys
+                    .iter()
+                    .try_fold(doc.as_item(), |item, k| item.get(k))
+                    .ok_or_else(not_found)?;
+                let old = item.as_str().ok_or_else(not_string)?.to_string();
+                if !document::set_string(&mut doc, &keys, version) {
+                    return Err(not_string());
+                }
+                Ok((old, doc.to_string()))
+            }
+            Locator::Json(pointer) => {
+                serde_json::from_str::<serde_json::Value>(text)
+                    .map_err(|e| SyncError::Json(path.to_path_buf(), e))?;
+                let range = json_span(text, pointer).ok_or_else(not_found)?;
+                let old: String =
+                    serde_json::from_str(&text[range.clone()]).map_err(|_| not_string())?;
+                let new = serde_json::to_string(version)
+                    .map_err(|e| SyncError::Json(path.to_path_buf(), e))?;
+                Ok((old, splice(text, range, &new)))
+            }
+            Locator::Regex(pattern) => {
+                let re = Regex::new(pattern).map_err(SyncError::Regex)?;
+                if re.captures_len() < 2 {
+                    return Err(SyncError::NoCaptureGroup(pattern.clone()));
+                }
+                let ranges: Vec<Range<usize>> = re
+                    .captures_iter(text)
+                    .filter_map(|c| c.get(1))
+                    .map(|m| m.range())
+                    .collect();
+                let first = ranges.first().ok_or_else(not_found)?;
+                // 古い値が異なる箇所があれば最初のものを報告する
+                let old = ranges
+                    .iter()
+                    .map(|r| &text[r.clone()])
+                    .find(|s| *s != version)
+                    .unwrap_or(&text[first.clone()])
+                    .to_string();
+                let mut updated = text.to_string();
+                for r in ranges.into_iter().rev() {
+                    updated = splice(&updated, r, version);
+                }
+                Ok((old, updated))
+            }
+        }
+    }
+}
+
+pub(crate) fn splice(text: &str, range: Range<usize>, s: &str) -> String {
+    let mut out = String::with_capacity(text.len() + s.len());
+    out.push_str(&text[..range.start]);
+    out.push_str(s);
+    out.push_str(&text[range.end..]);
+    out
+}
+
+// Note: 値の位置だけが必要なので`serde_json`で再シリアライズせずに自前で走査する。
+// 再シリアライズすると配列の改行やエスケープの書き方まで変わってしまうため。
+// 構文の検証は事前に`serde_json`で済ませている前提で、ここでは寛容に読む。
+
+/// Byte range of the value at JSON `pointer` (RFC 6901) in `text`.
+pub(crate) fn json_span(text: &str, pointer: &str) -> Option<Range<usize>> {
+    let tokens: Vec<String> = match pointer {
+        "" => vec![],
+        p => p
+            .strip_prefix('/')?
+            .split('/')
+            .map(|t| t.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    };
+    let mut scanner = JsonScanner {
+        s: text.as_bytes(),
+        pos: 0,
+    };
+    scanner.find(&tokens)
+}
+
+struct JsonScanner<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl JsonScanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Option<()> {
+        self.skip_ws();
+        (self.peek()? == c).then(|| self.pos += 1)
+    }
+
+    /// Find the value at `path` from the current position.
+    fn find(&mut self, path: &[String]) -> Option<Range<usize>> {
+        self.skip_ws();
+        let Some((token, rest)) = path.split_first() else {
+            let start = self.pos;
+            self.skip_value()?;
+            return Some(start..self.pos);
+        };
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    self.skip_ws();
+                    if self.peek()? == b'}' {
+                        return None;
+                    }
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    if key == *token {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_ws();
+                    if self.peek()? == b',' {
+                        self.pos += 1;
+                    }
+                }
+            }
+            b'[' => {
+                let index: usize = token.parse().ok()?;
+                self.pos += 1;
+                for i in 0.. {
+                    self.skip_ws();
+                    if self.peek()? == b']' {
+                        return None;
+                    }
+                    if i == index {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    self.skip_ws();
+                    if self.peek()? == b',' {
+                        self.pos += 1;
+                    }
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Read a string and return it unescaped.
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.skip_string()?;
+        serde_json::from_slice(&self.s[start..self.pos]).ok()
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        self.expect(b'"')?;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        self.skip_ws();
+        match self.peek()? {
+            b'"' => self.skip_string(),
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.skip_ws();
+                    match self.peek()? {
+                        c if c == close => {
+                            self.pos += 1;
+                            return Some(());
+                        }
+                        b',' | b':' => self.pos += 1,
+                        _ => self.skip_value()?,
+                    }
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, b',' | b'}' | b']') && !c.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+                Some(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn test_json_span() {
+        let text = r#"{
+  "name": "a\"b",
+  "files": ["x", "y"],
+  "nested": { "a/b": { "version": "1.0.0" } },
+  "version": "0.1.0"
+}"#;
+        let at = |p: &str| json_span(text, p).map(|r| &text[r]);
+        assert_eq!(at("/version"), Some(r#""0.1.0""#));
+        assert_eq!(at("/nested/a~1b/version"), Some(r#""1.0.0""#));
+        assert_eq!(at("/files/1"), Some(r#""y""#));
+        assert_eq!(at("/name"), Some(r#""a\"b""#));
+        assert_eq!(at("/missing"), None);
+        assert_eq!(at("/files/2"), None);
+    }
+
+    #[test]
+    fn test_replace() {
+        let path = Path::new("x");
+        let toml = Locator::Toml("package.version".to_string());
+        assert_eq!(
+            toml.replace(path, "[package]\nversion = \"0.1.0\" # v\n", "0.2.0")
+                .unwrap(),
+            (
+                "0.1.0".to_string(),
+                "[package]\nversion = \"0.2.0\" # v\n".to_string()
+            )
+        );
+        assert!(matches!(
+            toml.replace(path, "[package]\nname = \"a\"\n", "0.2.0"),
+            Err(SyncError::NotFound(..))
+        ));
+
+        let json = Locator::Json("/version".to_string());
+        assert_eq!(
+            json.replace(path, "{\"files\": [1,2],\n \"version\":\"0.1.0\"}", "0.2.0")
+                .unwrap()
+                .1,
+            "{\"files\": [1,2],\n \"version\":\"0.2.0\"}"
+        );
+        assert!(matches!(
+            Locator::Json("/files".to_string()).replace(path, "{\"files\": [1]}", "0.2.0"),
+            Err(SyncError::NotString(..))
+        ));
+
+        let regex = Locator::Regex(r#"VERSION "([^"]*)""#.to_string());
+        assert_eq!(
+            regex
+                .replace(
+                    path,
+                    "#define VERSION \"0.1.0\"\n// VERSION \"0.0.9\"\n",
+                    "0.2.0"
+                )
+                .unwrap(),
+            (
+                "0.1.0".to_string(),
+                "#define VERSION \"0.2.0\"\n// VERSION \"0.2.0\"\n".to_string()
+            )
+        );
+        assert!(matches!(
+            Locator::Regex("VERSION".to_string()).replace(path, "VERSION", "0.2.0"),
+            Err(SyncError::NoCaptureGroup(_))
+        ));
+    }
+
+    #[test]
+    fn test_sync() {
+        let dir = env::temp_dir().join(format!("fuver-sync-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nversion = \"0.1.0\"\n").unwrap();
+        fs::write(dir.join("package.json"), "{ \"version\": \"0.1.1\" }\n").unwrap();
+
+        let config: SyncConfig = toml::from_str(
+            r#"targets = [
+                { file = "Cargo.toml", toml = "package.version" },
+                { file = "package.json", json = "/version" },
+            ]"#,
+        )
+        .unwrap();
+
+        match config.sync(&dir, "0.1.1", true) {
+            Err(SyncError::OutOfDate(files)) => assert_eq!(files, [PathBuf::from("Cargo.toml")]),
+            r => panic!("Expected OutOfDate: {:?}", r),
+        }
+        assert_eq!(
+            fs::read_to_string(dir.join("Cargo.toml")).unwrap(),
+            "[package]\nversion = \"0.1.0\"\n"
+        );
+
+        let results = config.sync(&dir, "0.1.1", false).unwrap();
+        assert!(results[0].is_changed());
+        assert!(!results[1].is_changed());
+        assert_eq!(
+            fs::read_to_string(dir.join("Cargo.toml")).unwrap(),
+            "[package]\nversion = \"0.1.1\"\n"
+        );
+        assert!(config.sync(&dir, "0.1.1", true).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sync_same_file() {
+        let dir = env::temp_dir().join(format!("fuver-sync-same-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nversion = \"0.0.0\"\n\n[workspace.package]\nversion = \"0.0.0\"\n",
+        )
+        .unwrap();
+        let config: SyncConfig = toml::from_str(
+            r#"targets = [
+                { file = "Cargo.toml", toml = "package.version" },
+                { file = "Cargo.toml", toml = "workspace.package.version" },
+            ]"#,
+        )
+        .unwrap();
+
+        let edits = config.plan(&dir, "0.1.0").unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].results.len(), 2);
+
+        let results = config.sync(&dir, "0.1.0", false).unwrap();
+        assert!(results.iter().all(SyncResult::is_changed));
+        assert_eq!(
+            fs::read_to_string(dir.join("Cargo.toml")).unwrap(),
+            "[package]\nversion = \"0.1.0\"\n\n[workspace.package]\nversion = \"0.1.0\"\n"
+        );
+        assert!(config.sync(&dir, "0.1.0", true).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
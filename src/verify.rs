@@ -0,0 +1,223 @@
+use std::{
+    error, fmt,
+    path::{Path, PathBuf},
+    result,
+};
+
+use git2::Repository;
+use serde::Serialize;
+
+use crate::fuver::FuVer;
+use crate::git::{self, GitConfig};
+
+pub type Result<T> = result::Result<T, VerifyError>;
+
+#[derive(Debug)]
+pub enum VerifyError {
+    Git(git2::Error),
+    /// the tag of the version is not the one being built
+    TagMismatch {
+        expected: String,
+        found: Vec<String>,
+    },
+    /// the config file has uncommitted changes
+    Uncommitted(PathBuf),
+    /// the version is not greater than the previous release tag
+    NotIncreased {
+        version: String,
+        previous: String,
+    },
+}
+
+impl VerifyError {
+    /// Exit code of the failure. Failures other than the checks exit with 1.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VerifyError::Git(_) => 1,
+            VerifyError::TagMismatch { .. } => 3,
+            VerifyError::Uncommitted(_) => 4,
+            VerifyError::NotIncreased { .. } => 5,
+        }
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Git(e) => write!(f, "{}", e),
+            VerifyError::TagMismatch { expected, found } if found.is_empty() => {
+                write!(f, "タグ {} がHEADにありません", expected)
+            }
+            VerifyError::TagMismatch { expected, found } => write!(
+                f,
+                "タグがバージョンと一致しません: {} (期待値 {})",
+                found.join(", "),
+                expected
+            ),
+            VerifyError::Uncommitted(p) => {
+                write!(f, "{} がコミットされていません", p.display())
+            }
+            VerifyError::NotIncreased { version, previous } => write!(
+                f,
+                "バージョン {} が前回のリリース {} より大きくありません",
+                version, previous
+            ),
+        }
+    }
+}
+
+impl error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            VerifyError::Git(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<git2::Error> for VerifyError {
+    fn from(e: git2::Error) -> Self {
+        VerifyError::Git(e)
+    }
+}
+
+/// Result of a successful [`verify`]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Verified {
+    pub tag: String,
+    /// highest release tag before `tag`
+    pub previous: Option<String>,
+}
+
+impl fmt::Display for Verified {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.previous {
+            Some(p) => write!(f, "Verified {} (previous {})", self.tag, p),
+            None => write!(f, "Verified {} (first release)", self.tag),
+        }
+    }
+}
+
+/// Check that `version` is ready to be released from HEAD.
+///
+/// `tag` is the tag being built (e.g. `GITHUB_REF_NAME`). When it is not
+/// given, the tags at HEAD are used.
+///
+/// # Errors
+/// Checked in this order:
+/// * the tag is not the tag of `version` ([`VerifyError::TagMismatch`])
+/// * `config_path` has uncommitted changes ([`VerifyError::Uncommitted`])
+/// * `version` is not greater than every other release tag reachable from HEAD
+///   ([`VerifyError::NotIncreased`])
+pub fn verify(
+    repo: &Repository,
+    config: &GitConfig,
+    config_path: &Path,
+    version: &FuVer,
+    tag: Option<&str>,
+) -> Result<Verified> {
+    let expected = config.tag_name(&version.core_string());
+    let found = match tag {
+        Some(t) => vec![t.strip_prefix("refs/tags/").unwrap_or(t).to_string()],
+        None => tags_at_head(repo)?,
+    };
+    if !found.contains(&expected) {
+        return Err(VerifyError::TagMismatch { expected, found });
+    }
+
+    if !is_committed(repo, config_path)? {
+        return Err(VerifyError::Uncommitted(config_path.to_path_buf()));
+    }
+
+    let previous = git::release_tags(repo, config)?
+        .into_iter()
+        .rfind(|t| t.name != expected);
+    if let Some(p) = &previous {
+        if p.version >= *version {
+            return Err(VerifyError::NotIncreased {
+                version: version.core_string(),
+                previous: p.name.clone(),
+            });
+        }
+    }
+    Ok(Verified {
+        tag: expected,
+        previous: previous.map(|t| t.name),
+    })
+}
+
+fn tags_at_head(repo: &Repository) -> result::Result<Vec<String>, git2::Error> {
+    let head = repo.head()?.peel_to_commit()?.id();
+    let mut names = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        if git::tag_commit(repo, name) == Some(head) {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// `path` is tracked and has no changes in the index or the worktree.
+fn is_committed(repo: &Repository, path: &Path) -> result::Result<bool, git2::Error> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(false);
+    };
+    let (Ok(workdir), Ok(path)) = (workdir.canonicalize(), path.canonicalize()) else {
+        return Ok(false);
+    };
+    let Ok(relative) = path.strip_prefix(&workdir) else {
+        return Ok(false);
+    };
+    Ok(repo.status_file(relative)?.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::{commit, tag, temp_repo};
+    use std::fs;
+
+    #[test]
+    fn test_verify() {
+        let (dir, repo) = temp_repo("verify");
+        let path = dir.join("fuver.toml");
+        let config = GitConfig::default();
+        fs::write(&path, "1.0.0").unwrap();
+        commit(&repo, "release 1.0.0");
+        tag(&repo, "v1.0.0");
+        fs::write(&path, "1.1.0").unwrap();
+        commit(&repo, "release 1.1.0");
+
+        let version = FuVer::parse("1.1.0").unwrap();
+        let err = verify(&repo, &config, &path, &version, None).unwrap_err();
+        assert_eq!(err.exit_code(), 3);
+        let err = verify(&repo, &config, &path, &version, Some("v1.0.0")).unwrap_err();
+        assert_eq!(err.exit_code(), 3);
+
+        tag(&repo, "v1.1.0");
+        let blob = repo.blob(b"not a commit").unwrap();
+        repo.tag_lightweight("blob", &repo.find_object(blob, None).unwrap(), false)
+            .unwrap();
+        assert_eq!(
+            verify(&repo, &config, &path, &version, None).unwrap().tag,
+            "v1.1.0"
+        );
+        assert_eq!(
+            verify(&repo, &config, &path, &version, Some("refs/tags/v1.1.0")).unwrap(),
+            Verified {
+                tag: "v1.1.0".to_string(),
+                previous: Some("v1.0.0".to_string()),
+            }
+        );
+
+        fs::write(&path, "1.1.0 edited").unwrap();
+        let err = verify(&repo, &config, &path, &version, None).unwrap_err();
+        assert_eq!(err.exit_code(), 4);
+        fs::write(&path, "1.1.0").unwrap();
+
+        tag(&repo, "v2.0.0");
+        let err = verify(&repo, &config, &path, &version, None).unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,30 +1,89 @@
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
-use std::{fmt, ops::AddAssign, result, str::FromStr};
+use std::{error, fmt, ops::AddAssign, result, str::FromStr};
 
 #[derive(Debug)]
 pub enum VersionError {
     Format(String),
+    /// `part` is one of `major`, `minor` or `patch`
+    InvalidPart {
+        part: &'static str,
+        value: String,
+    },
 }
 
 impl fmt::Display for VersionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             VersionError::Format(s) => write!(f, "バージョンが不正です: {}", s),
+            VersionError::InvalidPart { part, value } => {
+                write!(f, "{}バージョンが不正です: {}", part, value)
+            }
         }
     }
 }
+impl error::Error for VersionError {}
+
 pub type Result<T> = result::Result<T, VersionError>;
 
-fn part_version(version: &str) -> Result<Vec<usize>> {
+const PART_NAMES: [&str; 3] = ["major", "minor", "patch"];
+
+/// Split `x.y.z` into numbers.
+///
+/// In strict mode non-numeric parts, leading zeros and overflow are errors.
+/// Otherwise unparsable parts are treated as 0, which is only meant for masks.
+fn part_version(version: &str, strict: bool) -> Result<Vec<usize>> {
     let sp: Vec<&str> = version.split(".").collect();
     if sp.iter().len() != 3 {
         return Err(VersionError::Format(version.to_string()));
     }
 
-    Ok(sp.iter().map(|n| n.parse::<usize>().unwrap_or(0)).collect())
+    if !strict {
+        return Ok(sp.iter().map(|n| n.parse::<usize>().unwrap_or(0)).collect());
+    }
+
+    sp.iter()
+        .zip(PART_NAMES)
+        .map(|(n, part)| parse_part(n, part))
+        .collect()
+}
+
+fn parse_part(n: &str, part: &'static str) -> Result<usize> {
+    let invalid = || VersionError::InvalidPart {
+        part,
+        value: n.to_string(),
+    };
+    if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if n.len() > 1 && n.starts_with('0') {
+        return Err(invalid());
+    }
+    n.parse::<usize>().map_err(|_| invalid())
+}
+
+/// Part of the core version. Ordered from the highest part (`Major < Minor < Patch`).
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionPart {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl fmt::Display for VersionPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionPart::Major => write!(f, "major"),
+            VersionPart::Minor => write!(f, "minor"),
+            VersionPart::Patch => write!(f, "patch"),
+        }
+    }
 }
 
-#[derive(Deserialize, Clone)]
+/// Core version `major.minor.patch`
+///
+/// Ordering follows SemVer 2.0: fields are compared numerically from major to patch.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version {
     major: usize,
     minor: usize,
@@ -50,7 +109,7 @@ impl Serialize for Version {
 impl FromStr for Version {
     type Err = VersionError;
     fn from_str(s: &str) -> result::Result<Self, Self::Err> {
-        let parts = part_version(s)?;
+        let parts = part_version(s, true)?;
         Ok(Self {
             major: parts[0],
             minor: parts[1],
@@ -152,6 +211,15 @@ impl Version {
         Ok(())
     }
 
+    /// Increment the given part. lower parts are reset to 0.
+    pub fn increment(&mut self, part: VersionPart) -> Result<()> {
+        match part {
+            VersionPart::Major => self.increment_major(),
+            VersionPart::Minor => self.increment_minor(),
+            VersionPart::Patch => self.increment_patch(),
+        }
+    }
+
     /// Increment using mask
     ///
     /// Mask format is `x.x.x`. `x` can be any non-numeric.
@@ -164,23 +232,8 @@ impl Version {
     ///
     /// The increment can be positive number. `x.2.x` will result `0.3.0`.
     pub fn increment_mask(&mut self, mask: &str) -> Result<()> {
-        let mask_version = Version::from_str(mask)?;
-        *self += mask_version;
-        Ok(())
-    }
-
-    pub fn show_major(&self) -> Result<()> {
-        println!("{}", self.get_major());
-        Ok(())
-    }
-
-    pub fn show_minor(&self) -> Result<()> {
-        println!("{}", self.get_minor());
-        Ok(())
-    }
-
-    pub fn show_patch(&self) -> Result<()> {
-        println!("{}", self.get_patch());
+        let parts = part_version(mask, false)?;
+        *self += Version::new(parts[0], parts[1], parts[2]);
         Ok(())
     }
 }
@@ -234,13 +287,13 @@ mod tests {
     fn test_getters_and_setters() {
         let mut version = Version::new(1, 2, 3);
 
-        version.set_major(4);
+        version.set_major(4).unwrap();
         assert_eq!(version.get_major(), 4);
 
-        version.set_minor(5);
+        version.set_minor(5).unwrap();
         assert_eq!(version.get_minor(), 5);
 
-        version.set_patch(6);
+        version.set_patch(6).unwrap();
         assert_eq!(version.get_patch(), 6);
 
         assert_eq!(version.to_string(), "4.5.6");
@@ -320,15 +373,62 @@ mod tests {
     #[test]
     fn test_part_version() {
         // 正常なバージョン文字列
-        let parts = part_version("1.2.3").unwrap();
+        let parts = part_version("1.2.3", true).unwrap();
         assert_eq!(parts, vec![1, 2, 3]);
 
-        // 数値ではない部分を含むバージョン文字列
-        let parts = part_version("1.a.3").unwrap();
+        // 数値ではない部分はマスク用の非厳格モードでのみ0として扱う
+        let parts = part_version("1.a.3", false).unwrap();
         assert_eq!(parts, vec![1, 0, 3]);
 
         // 不正なフォーマット
-        let result = part_version("1.2");
+        let result = part_version("1.2", true);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_part_version_strict() {
+        let invalid_part = |s: &str| match part_version(s, true) {
+            Err(VersionError::InvalidPart { part, value }) => (part, value),
+            _ => panic!("Expected VersionError::InvalidPart: {}", s),
+        };
+
+        assert_eq!(invalid_part("1.a.3"), ("minor", "a".to_string()));
+        assert_eq!(invalid_part("01.2.3"), ("major", "01".to_string()));
+        assert_eq!(invalid_part("1.2."), ("patch", "".to_string()));
+        assert_eq!(invalid_part("1.2.-3"), ("patch", "-3".to_string()));
+
+        let overflow = format!("1.2.{}0", usize::MAX);
+        assert_eq!(invalid_part(&overflow).0, "patch");
+
+        assert_eq!(part_version("0.0.0", true).unwrap(), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_set_strict() {
+        let mut version = Version::new(1, 2, 3);
+        assert!(version.set("1.a.3").is_err());
+        assert_eq!(version.to_string(), "1.2.3");
+
+        // マスクは数値以外を許容する
+        version.increment_mask("x.x.1").unwrap();
+        assert_eq!(version.to_string(), "1.2.4");
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(Version::new(1, 0, 0) < Version::new(2, 0, 0));
+        assert!(Version::new(2, 0, 0) < Version::new(2, 1, 0));
+        assert!(Version::new(2, 1, 0) < Version::new(2, 1, 1));
+        assert!(Version::new(1, 10, 0) > Version::new(1, 9, 99));
+        assert_eq!(Version::new(1, 2, 3), Version::from_str("1.2.3").unwrap());
+
+        let mut versions = [
+            Version::new(1, 0, 10),
+            Version::new(0, 9, 0),
+            Version::new(1, 0, 2),
+        ];
+        versions.sort();
+        let sorted: Vec<String> = versions.iter().map(|v| v.to_string()).collect();
+        assert_eq!(sorted, vec!["0.9.0", "1.0.2", "1.0.10"]);
+    }
 }
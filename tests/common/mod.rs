@@ -0,0 +1,57 @@
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use git2::{Repository, Signature};
+
+/// Empty directory in the temporary directory
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("fuver-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Empty repository in [`temp_dir`]
+pub fn temp_repo(name: &str) -> (PathBuf, Repository) {
+    let dir = temp_dir(name);
+    let repo = Repository::init(&dir).unwrap();
+    (dir, repo)
+}
+
+/// Run fuver with `config` and return stdout. Panics when it fails.
+pub fn fuver(config: &Path, args: &[&str]) -> String {
+    let out = Command::new(env!("CARGO_BIN_EXE_fuver"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    String::from_utf8(out.stdout).unwrap()
+}
+
+/// Commit all files in the worktree
+pub fn commit(repo: &Repository, message: &str) {
+    let sig = Signature::now("fuver", "fuver@example.com").unwrap();
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+        .unwrap();
+}
+
+/// Lightweight tag at HEAD
+pub fn tag(repo: &Repository, name: &str) {
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    repo.tag_lightweight(name, head.as_object(), false).unwrap();
+}
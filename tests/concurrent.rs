@@ -0,0 +1,79 @@
+mod common;
+
+use std::{collections::HashSet, fs, process::Command, thread};
+
+use common::temp_dir;
+
+const JOBS: usize = 16;
+
+#[test]
+fn test_concurrent_incr_build() {
+    let dir = temp_dir("concurrent");
+    let config = dir.join("fuver.toml");
+    fs::write(
+        &config,
+        r#"[version]
+major = 1
+minor = 0
+patch = 0
+
+[build]
+number = 0
+date = "2025-03-04T01:14:25+09:00"
+hash = "cc5048825cf821ae2a8db3eeadbaed9f548cc04a"
+format = "{number}"
+"#,
+    )
+    .unwrap();
+
+    let handles: Vec<_> = (0..JOBS)
+        .map(|_| {
+            let config = config.clone();
+            thread::spawn(move || {
+                let out = Command::new(env!("CARGO_BIN_EXE_fuver"))
+                    .arg("--config")
+                    .arg(&config)
+                    .args(["--lock-timeout", "30", "incr", "build", "number"])
+                    .output()
+                    .unwrap();
+                String::from_utf8(out.stdout).unwrap()
+            })
+        })
+        .collect();
+
+    // 出力例: Increment Build Number 0 -> 1 (format = "{number}")
+    let numbers: Vec<usize> = handles
+        .into_iter()
+        .map(|h| {
+            let out = h.join().unwrap();
+            let new = out.trim().rsplit(" -> ").next().unwrap().to_string();
+            new.parse().unwrap()
+        })
+        .collect();
+
+    let unique: HashSet<usize> = numbers.iter().copied().collect();
+    assert_eq!(
+        unique.len(),
+        JOBS,
+        "duplicated build numbers: {:?}",
+        numbers
+    );
+    assert_eq!(unique, (1..=JOBS).collect());
+
+    let saved = fs::read_to_string(&config).unwrap();
+    assert!(saved.contains(&format!("number = {}", JOBS)));
+    assert!(!dir.join(".fuver.toml.lock").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_invalid_lock_timeout() {
+    for timeout in ["inf", "nan", "1e30", "-1"] {
+        let out = Command::new(env!("CARGO_BIN_EXE_fuver"))
+            .args(["--lock-timeout", timeout, "show"])
+            .output()
+            .unwrap();
+        // clapの引数エラー (パニックなら101)
+        assert_eq!(out.status.code(), Some(2), "{}: {:?}", timeout, out);
+    }
+}
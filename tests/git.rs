@@ -0,0 +1,132 @@
+mod common;
+
+use std::{fs, process::Command};
+
+use common::{commit, fuver, tag, temp_repo};
+
+const CONFIG: &str = r#"[version]
+major = 1
+minor = 2
+patch = 3
+"#;
+
+#[test]
+fn test_bump() {
+    let (dir, repo) = temp_repo("bump");
+    let config = dir.join("fuver.toml");
+    fs::write(&config, CONFIG).unwrap();
+    commit(&repo, "chore: release 1.2.3");
+    tag(&repo, "v1.2.3");
+
+    commit(&repo, "docs: readme");
+    let out = fuver(&config, &["bump", "--auto"]);
+    assert!(out.starts_with("No commits since v1.2.3"), "{}", out);
+    assert_eq!(fs::read_to_string(&config).unwrap(), CONFIG);
+
+    commit(&repo, "fix: crash");
+    commit(&repo, "feat(cli): new command");
+    let json = fuver(&config, &["bump", "--dry-run", "-o", "json"]);
+    assert!(json.contains(r#""bump": "minor""#), "{}", json);
+    assert!(json.contains(r#""since": "v1.2.3""#), "{}", json);
+    assert!(json.contains(r#""diff": "--- "#), "{}", json);
+    assert_eq!(fs::read_to_string(&config).unwrap(), CONFIG);
+
+    let out = fuver(&config, &["bump"]);
+    assert!(out.contains("feat(cli): new command => minor"), "{}", out);
+    assert_eq!(fuver(&config, &["show"]), "1.3.0\n");
+
+    // 次のタグまでは何度実行しても同じ
+    let out = fuver(&config, &["bump"]);
+    assert!(out.ends_with("already bumped by minor\n"), "{}", out);
+    assert_eq!(fuver(&config, &["show"]), "1.3.0\n");
+    commit(&repo, "feat!: drop old config");
+    fuver(&config, &["bump", "-s"]);
+    assert_eq!(fuver(&config, &["show"]), "2.0.0\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_changelog() {
+    let (dir, repo) = temp_repo("changelog-cli");
+    let config = dir.join("fuver.toml");
+    fs::write(&config, CONFIG).unwrap();
+    let old = "# Changelog\n\n## 1.2.3 - 2025-01-01\n\n- first\n";
+    fs::write(dir.join("CHANGELOG.md"), old).unwrap();
+    commit(&repo, "chore: release 1.2.3");
+    tag(&repo, "v1.2.3");
+    commit(&repo, "feat: new command");
+    commit(&repo, "docs: readme");
+
+    fuver(&config, &["bump", "-s"]);
+    let out = fuver(&config, &["changelog"]);
+    assert!(out.starts_with("Prepend ## 1.3.0 - "), "{}", out);
+    let changelog = fs::read_to_string(dir.join("CHANGELOG.md")).unwrap();
+    assert!(
+        changelog.starts_with("# Changelog\n\n## 1.3.0 - "),
+        "{}",
+        changelog
+    );
+    assert!(changelog.contains("### Features\n\n- new command ("));
+    assert!(!changelog.contains("readme"));
+    assert!(changelog.ends_with(&old["# Changelog\n\n".len()..]));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_init_from_git() {
+    let (dir, repo) = temp_repo("init-git");
+    fs::write(dir.join("a"), "1").unwrap();
+    commit(&repo, "first");
+    tag(&repo, "release-2.0.0-rc.1");
+    tag(&repo, "release-1.9.0");
+    tag(&repo, "v9.0.0");
+    commit(&repo, "second");
+    commit(&repo, "third");
+
+    let config = dir.join("fuver.toml");
+    let file = config.to_str().unwrap();
+    let out = fuver(
+        &config,
+        &["init", file, "--from-git", "--prefix", "release-"],
+    );
+    // タグの行はstderr
+    assert!(out.starts_with("Initialize Success!\n"), "{}", out);
+    let json = fuver(&config, &["-o", "json", "show"]);
+    let head = repo.head().unwrap().peel_to_commit().unwrap().id();
+    assert!(
+        json.contains(&format!(r#""build_hash": "{}""#, head)),
+        "{}",
+        json
+    );
+    assert_eq!(
+        fuver(&config, &["show", "full"]).split('+').next(),
+        Some("2.0.0-rc.1")
+    );
+    assert_eq!(fuver(&config, &["show", "build", "number"]), "2\n");
+    let toml = fs::read_to_string(&config).unwrap();
+    assert!(toml.contains("tag = \"release-{version}\""), "{}", toml);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_verify_exit_code() {
+    let (dir, repo) = temp_repo("verify");
+    let config = dir.join("fuver.toml");
+    fs::write(&config, CONFIG).unwrap();
+    commit(&repo, "chore: release 1.2.3");
+
+    let status = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_fuver"))
+            .arg("--config")
+            .arg(&config)
+            .args(args)
+            .output()
+            .unwrap()
+            .status
+            .code()
+    };
+    assert_eq!(status(&["verify"]), Some(3));
+    assert_eq!(status(&["verify", "--tag", "v1.2.3"]), Some(0));
+    assert_eq!(status(&["show", "pre"]), Some(1));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::fs;
+
+use common::{fuver, temp_dir};
+
+const CONFIG: &str = r#"# 設定
+[version]
+major = 1
+minor = 2
+patch = 3
+
+[pre]
+tag = "rc"
+number = 1
+"#;
+
+#[test]
+fn test_next_does_not_write() {
+    let dir = temp_dir("next");
+    let config = dir.join("fuver.toml");
+    fs::write(&config, CONFIG).unwrap();
+
+    assert_eq!(fuver(&config, &["next", "pre"]), "1.2.3-rc.2\n");
+    assert_eq!(fuver(&config, &["next", "minor"]), "1.3.0\n");
+    assert_eq!(fuver(&config, &["next", "patch"]), "1.2.3\n");
+
+    let json = fuver(&config, &["next", "premajor", "--output", "json"]);
+    assert!(json.contains(r#""full": "2.0.0-alpha.1""#), "{}", json);
+
+    assert_eq!(fs::read_to_string(&config).unwrap(), CONFIG);
+    assert!(!dir.join(".fuver.toml.lock").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_next_build() {
+    let dir = temp_dir("next-build");
+    let config = dir.join("fuver.toml");
+    let toml = format!(
+        "{}\n[build]\nnumber = 4\ndate = \"2025-03-04T01:14:25+09:00\"\nhash = \"cc5048825cf821ae\"\nformat = \"build.{{number}}\"\n",
+        CONFIG
+    );
+    fs::write(&config, &toml).unwrap();
+
+    assert_eq!(
+        fuver(&config, &["next", "build", "number"]),
+        "1.2.3-rc.1+build.5\n"
+    );
+    assert_eq!(fuver(&config, &["next", "pre"]), "1.2.3-rc.2+build.4\n");
+    // show full と next は同じ書式
+    assert_eq!(fuver(&config, &["show", "full"]), "1.2.3-rc.1+build.4\n");
+    assert_eq!(fs::read_to_string(&config).unwrap(), toml);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_next_pre_without_pre_release() {
+    let dir = temp_dir("next-pre");
+    let config = dir.join("fuver.toml");
+    fs::write(&config, "[version]\nmajor = 1\nminor = 2\npatch = 3\n").unwrap();
+
+    // プレリリースがなければ次のパッチで始める (以前はパッチを上げなかった)
+    assert_eq!(fuver(&config, &["next", "pre"]), "1.2.4-alpha.1\n");
+    assert_eq!(fuver(&config, &["next", "pre", "rc"]), "1.2.4-rc.1\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_show_json_component() {
+    let dir = temp_dir("show-json");
+    let config = dir.join("fuver.toml");
+    fs::write(&config, CONFIG).unwrap();
+
+    let json = |args: &[&str]| {
+        let out = fuver(&config, &[&["-o", "json", "show"], args].concat());
+        serde_json::from_str::<serde_json::Value>(&out).unwrap()
+    };
+    assert_eq!(json(&["major"]), serde_json::json!({ "major": 1 }));
+    assert_eq!(
+        json(&["pre", "number"]),
+        serde_json::json!({ "pre_number": 1 })
+    );
+    assert_eq!(json(&["pre"]), serde_json::json!({ "pre": "rc.1" }));
+    assert_eq!(
+        json(&["version"]),
+        serde_json::json!({ "version": "1.2.3" })
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_dry_run_json_diff() {
+    let dir = temp_dir("dry-run-json");
+    let config = dir.join("fuver.toml");
+    fs::write(&config, CONFIG).unwrap();
+
+    let out = fuver(&config, &["--dry-run", "-o", "json", "incr", "minor"]);
+    let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(json["changes"][0]["new"], "1.3.0");
+    let diff = json["diff"].as_str().unwrap();
+    assert!(diff.contains("\n-minor = 2\n-patch = 3\n"), "{}", diff);
+    assert!(diff.contains("\n+minor = 3\n"), "{}", diff);
+    assert_eq!(fs::read_to_string(&config).unwrap(), CONFIG);
+    fs::remove_dir_all(&dir).unwrap();
+}